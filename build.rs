@@ -0,0 +1,132 @@
+fn main() {
+    #[cfg(feature = "capi")]
+    generate_header();
+    #[cfg(feature = "wasm")]
+    typescript::generate();
+}
+
+/// Generates the C header from `src/capi.rs` into `$OUT_DIR/zeropool_tx.h`, which the capi tests
+/// compare with the checked-in `include/zeropool_tx.h`.
+#[cfg(feature = "capi")]
+fn generate_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    cbindgen::generate(&crate_dir)
+        .expect("failed to generate the C header")
+        .write_to_file(format!("{out_dir}/zeropool_tx.h"));
+}
+
+/// TypeScript types for the wasm bindings, generated from `schema/tx.schema.json` into
+/// `$OUT_DIR/tx.d.ts`. Covers the subset of JSON Schema the schema uses.
+#[cfg(feature = "wasm")]
+mod typescript {
+    use std::fmt::Write;
+
+    use serde_json::{Map, Value};
+
+    pub fn generate() {
+        println!("cargo:rerun-if-changed=schema/tx.schema.json");
+
+        let schema: Value = serde_json::from_str(include_str!("schema/tx.schema.json"))
+            .expect("invalid schema/tx.schema.json");
+
+        let mut ts = String::new();
+        declaration(&mut ts, "Tx", &schema);
+        for (name, def) in schema["$defs"].as_object().unwrap() {
+            declaration(&mut ts, &type_name(name), def);
+        }
+
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        std::fs::write(format!("{out_dir}/tx.d.ts"), ts).unwrap();
+    }
+
+    fn declaration(ts: &mut String, name: &str, schema: &Value) {
+        doc_comment(ts, "", schema);
+        if schema["type"] == "object" {
+            writeln!(ts, "export interface {name} {}\n", object(schema, "")).unwrap();
+        } else {
+            writeln!(ts, "export type {name} = {};\n", type_of(schema, "")).unwrap();
+        }
+    }
+
+    fn doc_comment(ts: &mut String, indent: &str, schema: &Value) {
+        if let Some(description) = schema["description"].as_str() {
+            writeln!(ts, "{indent}/** {description} */").unwrap();
+        }
+    }
+
+    fn object(schema: &Value, indent: &str) -> String {
+        let empty = Map::new();
+        let properties = schema["properties"].as_object().unwrap_or(&empty);
+        let required = schema["required"].as_array();
+        let inner = format!("{indent}    ");
+
+        let mut ts = String::from("{\n");
+        for (name, property) in properties {
+            let optional =
+                !required.is_some_and(|required| required.contains(&name.as_str().into()));
+            doc_comment(&mut ts, &inner, property);
+            writeln!(
+                ts,
+                "{inner}{name}{}: {};",
+                if optional { "?" } else { "" },
+                type_of(property, &inner)
+            )
+            .unwrap();
+        }
+        ts.push_str(indent);
+        ts.push('}');
+        ts
+    }
+
+    fn type_of(schema: &Value, indent: &str) -> String {
+        if let Some(reference) = schema["$ref"].as_str() {
+            return type_name(reference.trim_start_matches("#/$defs/"));
+        }
+        if let Some(value) = schema.get("const") {
+            return value.to_string();
+        }
+        if let Some(values) = schema["enum"].as_array() {
+            return union(values.iter().map(Value::to_string));
+        }
+        if let Some(schemas) = schema["oneOf"].as_array() {
+            return union(schemas.iter().map(|schema| type_of(schema, indent)));
+        }
+
+        match schema["type"].as_str() {
+            Some("string") => "string".to_owned(),
+            Some("integer") | Some("number") => "number".to_owned(),
+            Some("boolean") => "boolean".to_owned(),
+            Some("object") => object(schema, indent),
+            Some("array") => match schema["prefixItems"].as_array() {
+                Some(items) => format!(
+                    "[{}]",
+                    items
+                        .iter()
+                        .map(|item| type_of(item, indent))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                None => format!("{}[]", type_of(&schema["items"], indent)),
+            },
+            _ => panic!("unsupported schema: {schema}"),
+        }
+    }
+
+    fn union(types: impl Iterator<Item = String>) -> String {
+        types.collect::<Vec<_>>().join(" | ")
+    }
+
+    /// `memoItem` -> `MemoItem`
+    fn type_name(name: &str) -> String {
+        let mut chars = name.chars();
+        chars
+            .next()
+            .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,98 @@
+#ifndef ZEROPOOL_TX_H
+#define ZEROPOOL_TX_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum ZpStatus {
+  ZP_STATUS_OK = 0,
+  // A required pointer argument is null.
+  ZP_STATUS_NULL_POINTER = 1,
+  // The chain or field value is not one of the enum's variants.
+  ZP_STATUS_INVALID_ARGUMENT = 2,
+  // The input ended before the transaction did.
+  ZP_STATUS_UNEXPECTED_EOF = 3,
+  // The input is malformed, e.g. an invalid field element, tx type or address.
+  ZP_STATUS_INVALID_DATA = 4,
+  // The transaction can't be encoded for the chain, e.g. it carries an unsupported asset id.
+  ZP_STATUS_INVALID_INPUT = 5,
+  // The output buffer is null or smaller than `*len`, which is set to the required size.
+  ZP_STATUS_BUFFER_TOO_SMALL = 6,
+  // Any other codec error.
+  ZP_STATUS_OTHER = 7,
+  // The codec panicked. This is a bug.
+  ZP_STATUS_PANIC = 8,
+} ZpStatus;
+
+typedef enum ZpChain {
+  ZP_CHAIN_EVM = 0,
+  ZP_CHAIN_NEAR = 1,
+  ZP_CHAIN_SUBSTRATE = 2,
+  ZP_CHAIN_WAVES = 3,
+  ZP_CHAIN_SOLANA = 4,
+  ZP_CHAIN_TRON = 5,
+  ZP_CHAIN_COSMWASM = 6,
+} ZpChain;
+
+typedef enum ZpField {
+  ZP_FIELD_NULLIFIER = 0,
+  ZP_FIELD_OUT_COMMIT = 1,
+  ZP_FIELD_DELTA = 2,
+  ZP_FIELD_ROOT_AFTER = 3,
+  ZP_FIELD_PROOF = 4,
+  ZP_FIELD_TREE_PROOF = 5,
+  ZP_FIELD_MEMO = 6,
+  ZP_FIELD_EXTRA_DATA = 7,
+  // Empty for the native coin, 32 bytes for `Id32`, UTF-8 for account ids, 20 bytes for
+  // ERC-20 addresses.
+  ZP_FIELD_ASSET_ID = 8,
+  // The chain-independent transaction id.
+  ZP_FIELD_ID = 9,
+} ZpField;
+
+// Decoded transaction. Only accessible through pointers.
+typedef struct ZpTxData ZpTxData;
+
+// Decodes `len` bytes at `data` as a transaction of `chain` and stores a new handle in `*out`.
+//
+// # Safety
+//
+// `data` must point to `len` readable bytes and `out` must be valid for writes.
+enum ZpStatus zp_tx_decode(uint32_t chain, const uint8_t *data, size_t len, struct ZpTxData **out);
+
+// Encodes the transaction for `chain` into `out`, see the module docs for buffer handling.
+//
+// # Safety
+//
+// `tx` must be a live handle, `len` must be valid for reads and writes and `out`, if not null,
+// must point to `*len` writable bytes.
+enum ZpStatus zp_tx_encode(const struct ZpTxData *tx, uint32_t chain, uint8_t *out, size_t *len);
+
+// Transaction type: 0 for deposits, 1 for transfers and 2 for withdrawals, or `u16::MAX` if
+// `tx` is null.
+//
+// # Safety
+//
+// `tx` must be a live handle or null.
+uint16_t zp_tx_type(const struct ZpTxData *tx);
+
+// Copies a field of the transaction into `out`, see the module docs for buffer handling.
+//
+// # Safety
+//
+// Same as for [`zp_tx_encode`].
+enum ZpStatus zp_tx_field(const struct ZpTxData *tx, uint32_t field, uint8_t *out, size_t *len);
+
+// Releases a handle returned by [`zp_tx_decode`]. Null is ignored.
+//
+// # Safety
+//
+// `tx` must be null or a handle that hasn't been freed yet.
+void zp_tx_free(struct ZpTxData *tx);
+
+#endif  /* ZEROPOOL_TX_H */
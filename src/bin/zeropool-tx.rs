@@ -0,0 +1,352 @@
+//! Inspects and converts pool transactions from the command line.
+
+use std::{
+    fs,
+    io::{self, Error, ErrorKind, Read, Result},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use base64::{prelude::BASE64_STANDARD, Engine as _};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use fawkes_crypto::{
+    backend::bellman_groth16::{
+        engines::{Bn256, Engine},
+        prover::Proof,
+    },
+    engines::bn256::Fr,
+};
+use zeropool_tx::{
+    cosmwasm::CosmWasm,
+    delta::Delta,
+    evm::Evm,
+    json::{BytesEncoding, ProofJson},
+    memo::MemoLayout,
+    near::Near,
+    solana::Solana,
+    substrate::Substrate,
+    tron::Tron,
+    waves::Waves,
+    Codec, TxType,
+};
+
+type TxData = zeropool_tx::TxData<Fr, Proof<Bn256>>;
+type TxJson = zeropool_tx::json::TxJson<Fr, <Bn256 as Engine>::Fq>;
+
+#[derive(Parser)]
+#[command(
+    name = "zeropool-tx",
+    version,
+    about = "Inspect and convert ZeroPool transactions"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print every field of a transaction, its delta and its memo.
+    Decode {
+        #[arg(long, value_enum)]
+        chain: Chain,
+        /// Print the JSON accepted by `encode` instead, as described by `schema/tx.schema.json`.
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        input: Input,
+    },
+    /// Encode a transaction from JSON.
+    Encode {
+        #[arg(long, value_enum)]
+        chain: Chain,
+        /// JSON of the transaction, as printed by `decode --json`.
+        #[arg(required_unless_present = "file")]
+        json: Option<String>,
+        /// Read the JSON from a file instead, `-` for stdin.
+        #[arg(long, conflicts_with = "json")]
+        file: Option<PathBuf>,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Re-encode a transaction for another chain.
+    Convert {
+        #[arg(long, value_enum)]
+        from: Chain,
+        #[arg(long, value_enum)]
+        to: Chain,
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Try to decode a transaction of unknown origin with every chain format.
+    Detect {
+        #[command(flatten)]
+        input: Input,
+    },
+}
+
+#[derive(Args)]
+struct Input {
+    /// Hex, optionally `0x`-prefixed, or base64.
+    #[arg(required_unless_present = "file")]
+    data: Option<String>,
+    /// Read raw bytes from a file instead, `-` for stdin.
+    #[arg(long, conflicts_with = "data")]
+    file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct Output {
+    /// Print base64 instead of hex.
+    #[arg(long)]
+    base64: bool,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum)]
+enum Chain {
+    Evm,
+    Near,
+    Substrate,
+    Waves,
+    Solana,
+    Tron,
+    Cosmwasm,
+}
+
+/// Runs `$body` with `$codec` bound to the codec type of `$chain`.
+macro_rules! with_codec {
+    ($chain:expr, $codec:ident => $body:expr) => {
+        match $chain {
+            Chain::Evm => {
+                type $codec = Evm;
+                $body
+            }
+            Chain::Near => {
+                type $codec = Near;
+                $body
+            }
+            Chain::Substrate => {
+                type $codec = Substrate;
+                $body
+            }
+            Chain::Waves => {
+                type $codec = Waves;
+                $body
+            }
+            Chain::Solana => {
+                type $codec = Solana;
+                $body
+            }
+            Chain::Tron => {
+                type $codec = Tron;
+                $body
+            }
+            Chain::Cosmwasm => {
+                type $codec = CosmWasm;
+                $body
+            }
+        }
+    };
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Decode { chain, json, input } => {
+            let tx = decode(chain, &input.read()?)?;
+            if json {
+                let json = with_codec!(chain, C => TxJson::new::<C, _>(&tx, BytesEncoding::Hex));
+                println!("{}", serde_json::to_string_pretty(&json)?);
+            } else {
+                with_codec!(chain, C => print_tx::<C>(&tx));
+            }
+        }
+        Command::Encode {
+            chain,
+            json,
+            file,
+            output,
+        } => {
+            let json = match (json, file) {
+                (Some(json), _) => json,
+                (None, Some(path)) => String::from_utf8(read_file(&path)?)
+                    .map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+                (None, None) => unreachable!("clap requires one of them"),
+            };
+            let tx: TxData = serde_json::from_str::<TxJson>(&json)?.to_tx_data()?;
+            output.print(&encode(chain, &tx)?);
+        }
+        Command::Convert {
+            from,
+            to,
+            input,
+            output,
+        } => {
+            let tx = decode(from, &input.read()?)?;
+            output.print(&encode(to, &tx)?);
+        }
+        Command::Detect { input } => {
+            let bytes = input.read()?;
+            let mut found = false;
+
+            for chain in Chain::value_variants() {
+                let name = chain.to_possible_value().unwrap();
+                let name = name.get_name();
+
+                match decode(*chain, &bytes) {
+                    Ok(tx) if encode(*chain, &tx).ok().as_deref() == Some(&bytes[..]) => {
+                        found = true;
+                        println!("{name}: {:?}", tx.tx_type);
+                    }
+                    Ok(tx) => {
+                        found = true;
+                        println!("{name}: {:?}, but re-encodes differently", tx.tx_type);
+                    }
+                    Err(err) => println!("{name}: no ({err})"),
+                }
+            }
+
+            if !found {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "not a transaction of any known chain",
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl Input {
+    fn read(&self) -> Result<Vec<u8>> {
+        match (&self.data, &self.file) {
+            (Some(data), _) => parse_bytes(data),
+            (None, Some(path)) => read_file(path),
+            (None, None) => unreachable!("clap requires one of them"),
+        }
+    }
+}
+
+impl Output {
+    fn print(&self, bytes: &[u8]) {
+        if self.base64 {
+            println!("{}", BASE64_STANDARD.encode(bytes));
+        } else {
+            println!("0x{}", hex::encode(bytes));
+        }
+    }
+}
+
+fn read_file(path: &PathBuf) -> Result<Vec<u8>> {
+    if path.as_os_str() == "-" {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    } else {
+        fs::read(path)
+    }
+}
+
+/// Hex takes precedence, since most hex strings are valid base64 as well.
+fn parse_bytes(data: &str) -> Result<Vec<u8>> {
+    let data = data.trim();
+    let hex = data.strip_prefix("0x").unwrap_or(data);
+
+    hex::decode(hex)
+        .or_else(|_| BASE64_STANDARD.decode(data))
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "input is neither hex nor base64"))
+}
+
+fn decode(chain: Chain, bytes: &[u8]) -> Result<TxData> {
+    with_codec!(chain, C => C::read(&mut &bytes[..]))
+}
+
+fn encode(chain: Chain, tx: &TxData) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    with_codec!(chain, C => C::write(tx, &mut bytes))?;
+    Ok(bytes)
+}
+
+fn print_tx<C: Codec>(tx: &TxData) {
+    println!("id:          0x{}", hex::encode(tx.id()));
+    if let Ok(hash) = tx.args_hash::<C>() {
+        println!("args hash:   0x{}", hex::encode(hash));
+    }
+    println!("tx type:     {:?}", tx.tx_type);
+    println!("nullifier:   {}", tx.nullifier);
+    println!("out commit:  {}", tx.out_commit);
+    println!("root after:  {}", tx.root_after);
+    println!("asset id:    {}", tx.asset_id);
+    print_proof("proof:      ", &tx.proof);
+    print_proof("tree proof: ", &tx.tree_proof);
+    println!("extra data:  0x{}", hex::encode(&tx.extra_data));
+
+    println!("delta:       {}", tx.delta);
+    match Delta::parse(&tx.delta) {
+        Some(delta) => {
+            println!("  token amount:  {}", delta.token_amount);
+            println!("  energy amount: {}", delta.energy_amount);
+            println!("  index:         {}", delta.index);
+            println!("  pool id:       {}", delta.pool_id);
+        }
+        None => println!("  invalid: bits above the pool id are set"),
+    }
+
+    println!("memo:        {} bytes", tx.memo.len());
+    match tx.fee::<C>() {
+        Ok(fee) => println!("  fee:           {fee}"),
+        Err(err) => println!("  fee:           invalid ({err})"),
+    }
+    if tx.tx_type == TxType::Withdraw {
+        match tx.withdraw_data::<C>() {
+            Ok(data) => {
+                println!("  native amount: {}", data.native_amount);
+                println!("  receiver:      {}", data.receiver);
+            }
+            Err(err) => println!("  withdraw data: invalid ({err})"),
+        }
+    }
+    match tx.parse_memo::<C>(MemoLayout::default()) {
+        Ok(memo) => {
+            println!("  ephemeral key: 0x{}", hex::encode(memo.ephemeral_key));
+            for item in memo.items() {
+                let kind = if item.index == 0 { "account" } else { "note" };
+                println!(
+                    "  {kind} {}: hash {}, {} bytes of ciphertext",
+                    item.index,
+                    item.hash,
+                    item.ciphertext.len()
+                );
+            }
+        }
+        Err(err) => println!("  message:       invalid ({err})"),
+    }
+}
+
+/// Coordinates as `0x`-prefixed big-endian hex, one point per line.
+fn print_proof(label: &str, proof: &Proof<Bn256>) {
+    let ProofJson { a, b, c } = ProofJson::new(proof);
+    println!("{label} a: ({}, {})", a[0], a[1]);
+    println!(
+        "{:w$} b: (({}, {}), ({}, {}))",
+        "",
+        b[0][0],
+        b[0][1],
+        b[1][0],
+        b[1][1],
+        w = label.len()
+    );
+    println!("{:w$} c: ({}, {})", "", c[0], c[1], w = label.len());
+}
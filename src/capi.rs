@@ -0,0 +1,406 @@
+//! C ABI over the canonical codecs, see `include/zeropool_tx.h`. Build the library with
+//! `cargo rustc --release --features capi --crate-type cdylib` (or `staticlib`).
+//!
+//! Decoding yields an opaque [`ZpTxData`] handle that must be released with [`zp_tx_free`].
+//! Functions that output bytes copy them into a caller-provided buffer: `*len` holds the buffer
+//! size on input and the number of bytes needed on output. If the buffer is null or too small,
+//! nothing is written and [`ZpStatus::BufferTooSmall`] is returned, so the caller can retry with
+//! a buffer of `*len` bytes. Field elements and proofs are written big-endian, as for EVM.
+//!
+//! Chains and fields are passed as `uint32_t` rather than as the enums, so that an out-of-range
+//! value coming from C is an error instead of undefined behavior.
+
+use std::{
+    io::{Error, ErrorKind, Result},
+    panic::{catch_unwind, AssertUnwindSafe},
+    slice,
+};
+
+use byteorder::BigEndian;
+use fawkes_crypto::{
+    backend::bellman_groth16::{engines::Bn256, prover::Proof},
+    engines::bn256::Fr,
+};
+
+use crate::{
+    cosmwasm::CosmWasm, evm::Evm, near::Near, proof::Proof as _, solana::Solana,
+    substrate::Substrate, tron::Tron, utils::write_num, waves::Waves, AssetId, Codec, TxData,
+};
+
+/// Decoded transaction. Only accessible through pointers.
+pub struct ZpTxData(TxData<Fr, Proof<Bn256>>);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ZpStatus {
+    Ok = 0,
+    /// A required pointer argument is null.
+    NullPointer = 1,
+    /// The chain or field value is not one of the enum's variants.
+    InvalidArgument = 2,
+    /// The input ended before the transaction did.
+    UnexpectedEof = 3,
+    /// The input is malformed, e.g. an invalid field element, tx type or address.
+    InvalidData = 4,
+    /// The transaction can't be encoded for the chain, e.g. it carries an unsupported asset id.
+    InvalidInput = 5,
+    /// The output buffer is null or smaller than `*len`, which is set to the required size.
+    BufferTooSmall = 6,
+    /// Any other codec error.
+    Other = 7,
+    /// The codec panicked. This is a bug.
+    Panic = 8,
+}
+
+impl From<Error> for ZpStatus {
+    fn from(err: Error) -> Self {
+        match err.kind() {
+            ErrorKind::UnexpectedEof => ZpStatus::UnexpectedEof,
+            ErrorKind::InvalidData => ZpStatus::InvalidData,
+            ErrorKind::InvalidInput => ZpStatus::InvalidInput,
+            _ => ZpStatus::Other,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ZpChain {
+    Evm = 0,
+    Near = 1,
+    Substrate = 2,
+    Waves = 3,
+    Solana = 4,
+    Tron = 5,
+    Cosmwasm = 6,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ZpField {
+    Nullifier = 0,
+    OutCommit = 1,
+    Delta = 2,
+    RootAfter = 3,
+    Proof = 4,
+    TreeProof = 5,
+    Memo = 6,
+    ExtraData = 7,
+    /// Empty for the native coin, 32 bytes for `Id32`, UTF-8 for account ids, 20 bytes for
+    /// ERC-20 addresses.
+    AssetId = 8,
+    /// The chain-independent transaction id.
+    Id = 9,
+}
+
+/// Runs `$body` with `$codec` bound to the codec type of `$chain`.
+macro_rules! with_codec {
+    ($chain:expr, $codec:ident => $body:expr) => {
+        match $chain {
+            ZpChain::Evm => {
+                type $codec = Evm;
+                $body
+            }
+            ZpChain::Near => {
+                type $codec = Near;
+                $body
+            }
+            ZpChain::Substrate => {
+                type $codec = Substrate;
+                $body
+            }
+            ZpChain::Waves => {
+                type $codec = Waves;
+                $body
+            }
+            ZpChain::Solana => {
+                type $codec = Solana;
+                $body
+            }
+            ZpChain::Tron => {
+                type $codec = Tron;
+                $body
+            }
+            ZpChain::Cosmwasm => {
+                type $codec = CosmWasm;
+                $body
+            }
+        }
+    };
+}
+
+/// Decodes `len` bytes at `data` as a transaction of `chain` and stores a new handle in `*out`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn zp_tx_decode(
+    chain: u32,
+    data: *const u8,
+    len: usize,
+    out: *mut *mut ZpTxData,
+) -> ZpStatus {
+    if (data.is_null() && len > 0) || out.is_null() {
+        return ZpStatus::NullPointer;
+    }
+    let Some(chain) = chain_from_u32(chain) else {
+        return ZpStatus::InvalidArgument;
+    };
+    let bytes = if len == 0 {
+        &[][..]
+    } else {
+        slice::from_raw_parts(data, len)
+    };
+
+    match guarded(|| with_codec!(chain, C => C::read(&mut &bytes[..]))) {
+        Ok(tx) => {
+            *out = Box::into_raw(Box::new(ZpTxData(tx)));
+            ZpStatus::Ok
+        }
+        Err(status) => status,
+    }
+}
+
+/// Encodes the transaction for `chain` into `out`, see the module docs for buffer handling.
+///
+/// # Safety
+///
+/// `tx` must be a live handle, `len` must be valid for reads and writes and `out`, if not null,
+/// must point to `*len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn zp_tx_encode(
+    tx: *const ZpTxData,
+    chain: u32,
+    out: *mut u8,
+    len: *mut usize,
+) -> ZpStatus {
+    let Some(tx) = tx.as_ref() else {
+        return ZpStatus::NullPointer;
+    };
+    let Some(chain) = chain_from_u32(chain) else {
+        return ZpStatus::InvalidArgument;
+    };
+
+    let bytes = guarded(|| {
+        let mut bytes = Vec::new();
+        with_codec!(chain, C => C::write(&tx.0, &mut bytes))?;
+        Ok(bytes)
+    });
+
+    match bytes {
+        Ok(bytes) => copy_out(&bytes, out, len),
+        Err(status) => status,
+    }
+}
+
+/// Transaction type: 0 for deposits, 1 for transfers and 2 for withdrawals, or `u16::MAX` if
+/// `tx` is null.
+///
+/// # Safety
+///
+/// `tx` must be a live handle or null.
+#[no_mangle]
+pub unsafe extern "C" fn zp_tx_type(tx: *const ZpTxData) -> u16 {
+    match tx.as_ref() {
+        Some(tx) => tx.0.tx_type as u16,
+        None => u16::MAX,
+    }
+}
+
+/// Copies a field of the transaction into `out`, see the module docs for buffer handling.
+///
+/// # Safety
+///
+/// Same as for [`zp_tx_encode`].
+#[no_mangle]
+pub unsafe extern "C" fn zp_tx_field(
+    tx: *const ZpTxData,
+    field: u32,
+    out: *mut u8,
+    len: *mut usize,
+) -> ZpStatus {
+    let Some(tx) = tx.as_ref() else {
+        return ZpStatus::NullPointer;
+    };
+    let Some(field) = field_from_u32(field) else {
+        return ZpStatus::InvalidArgument;
+    };
+
+    match field_bytes(&tx.0, field) {
+        Ok(bytes) => copy_out(&bytes, out, len),
+        Err(err) => err.into(),
+    }
+}
+
+/// Releases a handle returned by [`zp_tx_decode`]. Null is ignored.
+///
+/// # Safety
+///
+/// `tx` must be null or a handle that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn zp_tx_free(tx: *mut ZpTxData) {
+    if !tx.is_null() {
+        drop(Box::from_raw(tx));
+    }
+}
+
+fn chain_from_u32(chain: u32) -> Option<ZpChain> {
+    Some(match chain {
+        0 => ZpChain::Evm,
+        1 => ZpChain::Near,
+        2 => ZpChain::Substrate,
+        3 => ZpChain::Waves,
+        4 => ZpChain::Solana,
+        5 => ZpChain::Tron,
+        6 => ZpChain::Cosmwasm,
+        _ => return None,
+    })
+}
+
+fn field_from_u32(field: u32) -> Option<ZpField> {
+    Some(match field {
+        0 => ZpField::Nullifier,
+        1 => ZpField::OutCommit,
+        2 => ZpField::Delta,
+        3 => ZpField::RootAfter,
+        4 => ZpField::Proof,
+        5 => ZpField::TreeProof,
+        6 => ZpField::Memo,
+        7 => ZpField::ExtraData,
+        8 => ZpField::AssetId,
+        9 => ZpField::Id,
+        _ => return None,
+    })
+}
+
+fn field_bytes(tx: &TxData<Fr, Proof<Bn256>>, field: ZpField) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    match field {
+        ZpField::Nullifier => write_num::<BigEndian, _, Fr>(&mut bytes, &tx.nullifier)?,
+        ZpField::OutCommit => write_num::<BigEndian, _, Fr>(&mut bytes, &tx.out_commit)?,
+        ZpField::Delta => write_num::<BigEndian, _, Fr>(&mut bytes, &tx.delta)?,
+        ZpField::RootAfter => write_num::<BigEndian, _, Fr>(&mut bytes, &tx.root_after)?,
+        ZpField::Proof => tx.proof.write::<BigEndian, _>(&mut bytes)?,
+        ZpField::TreeProof => tx.tree_proof.write::<BigEndian, _>(&mut bytes)?,
+        ZpField::Memo => bytes.extend_from_slice(&tx.memo),
+        ZpField::ExtraData => bytes.extend_from_slice(&tx.extra_data),
+        ZpField::AssetId => match &tx.asset_id {
+            AssetId::None => {}
+            AssetId::Id32(id) => bytes.extend_from_slice(id),
+            AssetId::AccountId(account_id) => bytes.extend_from_slice(account_id.as_bytes()),
+            AssetId::Erc20(address) => bytes.extend_from_slice(address),
+        },
+        ZpField::Id => bytes.extend_from_slice(&tx.id()),
+    }
+
+    Ok(bytes)
+}
+
+/// Panics must not unwind into C.
+fn guarded<T>(f: impl FnOnce() -> Result<T>) -> std::result::Result<T, ZpStatus> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result.map_err(ZpStatus::from),
+        Err(_) => Err(ZpStatus::Panic),
+    }
+}
+
+unsafe fn copy_out(bytes: &[u8], out: *mut u8, len: *mut usize) -> ZpStatus {
+    let Some(len) = len.as_mut() else {
+        return ZpStatus::NullPointer;
+    };
+
+    let available = *len;
+    *len = bytes.len();
+
+    if bytes.is_empty() {
+        return ZpStatus::Ok;
+    }
+    if out.is_null() || available < bytes.len() {
+        return ZpStatus::BufferTooSmall;
+    }
+
+    out.copy_from_nonoverlapping(bytes.as_ptr(), bytes.len());
+    ZpStatus::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use fawkes_crypto::ff_uint::Num;
+
+    use super::*;
+    use crate::{
+        utils::tests::{sample_tx, TestTxData},
+        TxType,
+    };
+
+    #[test]
+    fn test_header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/zeropool_tx.h"));
+        assert!(
+            generated == include_str!("../include/zeropool_tx.h"),
+            "include/zeropool_tx.h is stale, regenerate it with \
+             `cbindgen --config cbindgen.toml --output include/zeropool_tx.h`"
+        );
+    }
+
+    #[test]
+    fn test_capi_decode_encode() {
+        let tx = TestTxData {
+            root_after: Num::from(4u64),
+            delta: Num::from(3u64),
+            out_commit: Num::from(2u64),
+            nullifier: Num::from(1u64),
+            memo: vec![1, 2, 3],
+            ..sample_tx(TxType::Transfer)
+        };
+        let mut evm = vec![];
+        Evm::write(&tx, &mut evm).unwrap();
+        let mut near = vec![];
+        Near::write(&tx, &mut near).unwrap();
+
+        unsafe {
+            let mut handle = ptr::null_mut();
+            let status = zp_tx_decode(ZpChain::Evm as u32, evm.as_ptr(), evm.len(), &mut handle);
+            assert_eq!(status, ZpStatus::Ok);
+            assert_eq!(zp_tx_type(handle), 1);
+
+            let mut len = 0;
+            let status = zp_tx_field(handle, ZpField::Nullifier as u32, ptr::null_mut(), &mut len);
+            assert_eq!((status, len), (ZpStatus::BufferTooSmall, 32));
+            let mut nullifier = [0u8; 32];
+            let status = zp_tx_field(
+                handle,
+                ZpField::Nullifier as u32,
+                nullifier.as_mut_ptr(),
+                &mut len,
+            );
+            assert_eq!(status, ZpStatus::Ok);
+            assert_eq!(nullifier[31], 1);
+
+            let mut buf = vec![0u8; near.len()];
+            let mut len = buf.len();
+            let status = zp_tx_encode(handle, ZpChain::Near as u32, buf.as_mut_ptr(), &mut len);
+            assert_eq!(status, ZpStatus::Ok);
+            assert_eq!(buf, near);
+
+            assert_eq!(
+                zp_tx_encode(handle, 7, buf.as_mut_ptr(), &mut len),
+                ZpStatus::InvalidArgument
+            );
+            zp_tx_free(handle);
+
+            let mut handle = ptr::null_mut();
+            let status = zp_tx_decode(ZpChain::Evm as u32, evm.as_ptr(), 100, &mut handle);
+            assert_eq!(status, ZpStatus::UnexpectedEof);
+            assert!(handle.is_null());
+
+            evm[4] = 0xff;
+            let status = zp_tx_decode(ZpChain::Evm as u32, evm.as_ptr(), evm.len(), &mut handle);
+            assert_eq!(status, ZpStatus::InvalidData);
+        }
+    }
+}
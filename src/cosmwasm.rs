@@ -0,0 +1,172 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use byteorder::LittleEndian;
+use fawkes_crypto::ff_uint::{Num, PrimeField};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hash::sha256,
+    proof::Proof,
+    utils::{read_borsh_string, write_borsh_string},
+    withdraw::{unexpected_address, ChainAddress},
+    AssetId, Codec, TxData, TxType,
+};
+
+/// `ExecuteMsg` of the pool contract. Serializes as `{"transact": {...}}`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", bound = "")]
+pub enum ExecuteMsg<Fr: PrimeField, P: Proof> {
+    Transact(TransactMsg<Fr, P>),
+}
+
+/// Same fields and encodings as the serde representation of [`TxData`], except that `memo` and
+/// `extra_data` are base64 strings like CosmWasm's `Binary`.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TransactMsg<Fr: PrimeField, P: Proof> {
+    pub tx_type: TxType,
+    pub proof: P,
+    pub tree_proof: P,
+    pub root_after: Num<Fr>,
+    pub delta: Num<Fr>,
+    pub out_commit: Num<Fr>,
+    pub nullifier: Num<Fr>,
+    #[serde(with = "crate::utils::serde_base64")]
+    pub memo: Vec<u8>,
+    #[serde(with = "crate::utils::serde_base64")]
+    pub extra_data: Vec<u8>,
+    /// Native denom or CW20 contract address of the pool asset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+}
+
+impl<Fr: PrimeField, P: Proof> TransactMsg<Fr, P> {
+    pub fn new(data: &TxData<Fr, P>) -> Result<Self> {
+        let asset = match data.asset_id.to_account_id()? {
+            "" => None,
+            asset => Some(asset.to_owned()),
+        };
+
+        Ok(Self {
+            tx_type: data.tx_type,
+            proof: data.proof.my_clone(),
+            tree_proof: data.tree_proof.my_clone(),
+            root_after: data.root_after,
+            delta: data.delta,
+            out_commit: data.out_commit,
+            nullifier: data.nullifier,
+            memo: data.memo.clone(),
+            extra_data: data.extra_data.clone(),
+            asset,
+        })
+    }
+}
+
+impl<Fr: PrimeField, P: Proof> From<TransactMsg<Fr, P>> for TxData<Fr, P> {
+    fn from(msg: TransactMsg<Fr, P>) -> Self {
+        TxData {
+            tx_type: msg.tx_type,
+            proof: msg.proof,
+            tree_proof: msg.tree_proof,
+            root_after: msg.root_after,
+            delta: msg.delta,
+            out_commit: msg.out_commit,
+            nullifier: msg.nullifier,
+            memo: msg.memo,
+            extra_data: msg.extra_data,
+            asset_id: AssetId::from_account_id(msg.asset.unwrap_or_default()),
+        }
+    }
+}
+
+/// Reads a JSON `ExecuteMsg`.
+pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
+    let ExecuteMsg::Transact(msg) =
+        serde_json::from_reader(r).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+
+    Ok(msg.into())
+}
+
+pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+    let msg = ExecuteMsg::Transact(TransactMsg::new(data)?);
+    serde_json::to_writer(w, &msg).map_err(Error::from)
+}
+
+/// JSON execute message of a CosmWasm pool contract. Hashed with sha256.
+pub struct CosmWasm;
+
+impl Codec for CosmWasm {
+    type MemoOrder = LittleEndian;
+
+    fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
+        read(r)
+    }
+
+    fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+        write(data, w)
+    }
+
+    fn read_withdraw_address<R: Read>(r: &mut R) -> Result<ChainAddress> {
+        Ok(ChainAddress::Cosmos(read_borsh_string(r)?))
+    }
+
+    fn write_withdraw_address<W: Write>(address: &ChainAddress, w: &mut W) -> Result<()> {
+        match address {
+            ChainAddress::Cosmos(address) => write_borsh_string(w, address),
+            _ => Err(unexpected_address(address)),
+        }
+    }
+
+    fn hash(bytes: &[u8]) -> [u8; 32] {
+        sha256(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        utils::tests::{sample_tx, TestTxData},
+        withdraw::WithdrawData,
+    };
+
+    #[test]
+    fn test_cosmwasm_write_read_withdraw() {
+        let withdraw_data = WithdrawData {
+            native_amount: 10,
+            receiver: ChainAddress::Cosmos(
+                "cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu".to_owned(),
+            ),
+        };
+        let mut memo = 1u64.to_le_bytes().to_vec();
+        CosmWasm::write_withdraw_data(&withdraw_data, &mut memo).unwrap();
+
+        let data = TestTxData {
+            root_after: Num::from(4u64),
+            delta: Num::from(3u64),
+            out_commit: Num::from(2u64),
+            nullifier: Num::from(1u64),
+            memo,
+            asset_id: AssetId::AccountId("uatom".to_owned()),
+            ..sample_tx(TxType::Withdraw)
+        };
+
+        let mut buf = vec![];
+        write(&data, &mut buf).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(json["transact"]["tx_type"], "0002");
+        assert_eq!(json["transact"]["nullifier"], "1");
+        assert_eq!(json["transact"]["extra_data"], "");
+        assert_eq!(json["transact"]["asset"], "uatom");
+
+        let data2: TestTxData = read(&mut &buf[..]).unwrap();
+        assert_eq!(data2, data);
+        assert_eq!(data2.asset_id, data.asset_id);
+        assert_eq!(data2.withdraw_data::<CosmWasm>().unwrap(), withdraw_data);
+
+        let mut invalid = withdraw_data;
+        invalid.receiver = ChainAddress::Cosmos("cosmos1invalid".to_owned());
+        assert!(CosmWasm::write_withdraw_data(&invalid, &mut vec![]).is_err());
+    }
+}
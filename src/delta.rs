@@ -0,0 +1,87 @@
+use fawkes_crypto::ff_uint::{Num, NumRepr, PrimeField, Uint};
+
+// Big-endian layout of the packed delta:
+// # unused             1 byte
+// # poolId             3 bytes
+// # txIndex            6 bytes
+// # energyAmount      14 bytes (signed)
+// # tokenAmount        8 bytes (signed)
+
+/// Components packed into `TxData::delta`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Delta {
+    pub token_amount: i64,
+    pub energy_amount: i128,
+    /// Index of the first leaf of `out_commit` in the pool's merkle tree.
+    pub index: u64,
+    pub pool_id: u32,
+}
+
+impl Delta {
+    pub const ENERGY_AMOUNT_BITS: u32 = 112;
+    pub const INDEX_BITS: u32 = 48;
+    pub const POOL_ID_BITS: u32 = 24;
+
+    /// Unpacks a delta. Returns `None` if bits above the pool id are set.
+    pub fn parse<Fr: PrimeField>(delta: &Num<Fr>) -> Option<Self> {
+        let mut bytes = [0u8; 32];
+        delta.to_uint().0.put_big_endian(&mut bytes);
+
+        if bytes[0] != 0 {
+            return None;
+        }
+
+        let mut pool_id = [0u8; 4];
+        pool_id[1..].copy_from_slice(&bytes[1..4]);
+        let mut index = [0u8; 8];
+        index[2..].copy_from_slice(&bytes[4..10]);
+        let mut energy_amount = [0u8; 16];
+        energy_amount[2..].copy_from_slice(&bytes[10..24]);
+        let mut token_amount = [0u8; 8];
+        token_amount.copy_from_slice(&bytes[24..32]);
+
+        // Sign-extend the 112-bit energy amount.
+        let energy_amount = i128::from_be_bytes(energy_amount) << 16 >> 16;
+
+        Some(Self {
+            token_amount: i64::from_be_bytes(token_amount),
+            energy_amount,
+            index: u64::from_be_bytes(index),
+            pool_id: u32::from_be_bytes(pool_id),
+        })
+    }
+
+    /// Packs the delta back into a field element. Components wider than their slot are
+    /// truncated.
+    pub fn to_num<Fr: PrimeField>(&self) -> Num<Fr> {
+        let mut bytes = [0u8; 32];
+        bytes[1..4].copy_from_slice(&self.pool_id.to_be_bytes()[1..]);
+        bytes[4..10].copy_from_slice(&self.index.to_be_bytes()[2..]);
+        bytes[10..24].copy_from_slice(&self.energy_amount.to_be_bytes()[2..]);
+        bytes[24..32].copy_from_slice(&self.token_amount.to_be_bytes());
+
+        Num::from_uint(NumRepr(Fr::Inner::from_big_endian(&bytes)))
+            .expect("248-bit value is always a valid field element")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fawkes_crypto::engines::bn256::Fr;
+
+    use super::*;
+
+    #[test]
+    fn test_delta_roundtrip() {
+        let delta = Delta {
+            token_amount: -1_000_000_000,
+            energy_amount: -(1 << 100),
+            index: (1 << 48) - 128,
+            pool_id: 0xffffff,
+        };
+
+        let num = delta.to_num::<Fr>();
+        assert_eq!(Delta::parse(&num), Some(delta));
+        assert_eq!(Delta::parse(&-Num::<Fr>::ONE), None);
+    }
+}
@@ -4,27 +4,88 @@ use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use fawkes_crypto::ff_uint::PrimeField;
 
 use crate::{
+    hash::keccak256,
+    policy::{NumPolicy, NumReader, NumReport},
     proof::Proof,
-    utils::{read_num, write_num},
-    TxData, TxType,
+    utils::write_num,
+    withdraw::{unexpected_address, ChainAddress},
+    AssetId, Codec, TxData, TxField, TxType,
 };
 
-const SELECTOR: &[u8] = &[0x8a, 0x40, 0x68, 0xdd];
+#[cfg(feature = "evm_tx")]
+pub mod envelope;
+pub mod gas;
+
+pub use gas::calldata_gas;
+
+/// Selector of the pool's `transact()` function.
+pub const SELECTOR: [u8; 4] = [0x8a, 0x40, 0x68, 0xdd];
 
 pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
+    read_with_selector(r, SELECTOR)
+}
+
+pub fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+    r: &mut R,
+    policy: NumPolicy,
+) -> Result<(TxData<Fr, P>, NumReport)> {
+    read_with_selector_and_policy(r, SELECTOR, policy)
+}
+
+pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+    write_with_selector(data, w, SELECTOR)
+}
+
+pub fn read_with_selector<R: Read, Fr: PrimeField, P: Proof>(
+    r: &mut R,
+    expected_selector: [u8; 4],
+) -> Result<TxData<Fr, P>> {
+    read_with_selector_and_policy(r, expected_selector, NumPolicy::Strict).map(|(data, _)| data)
+}
+
+pub fn read_with_selector_and_policy<R: Read, Fr: PrimeField, P: Proof>(
+    r: &mut R,
+    expected_selector: [u8; 4],
+    policy: NumPolicy,
+) -> Result<(TxData<Fr, P>, NumReport)> {
+    read_selector(r, expected_selector)?;
+
+    let mut nums = NumReader::new(policy);
+    let data = read_args(r, &mut nums)?;
+    Ok((data, nums.finish()?))
+}
+
+pub fn write_with_selector<W: Write, Fr: PrimeField, P: Proof>(
+    data: &TxData<Fr, P>,
+    w: &mut W,
+    selector: [u8; 4],
+) -> Result<()> {
+    data.asset_id.ensure_none()?;
+    w.write_all(&selector)?;
+    write_args(data, w)
+}
+
+fn read_selector<R: Read>(r: &mut R, expected_selector: [u8; 4]) -> Result<()> {
     let mut selector = [0u8; 4];
     r.read_exact(&mut selector)?;
 
-    if selector != SELECTOR {
+    if selector != expected_selector {
         return Err(Error::new(ErrorKind::InvalidData, "invalid selector"));
     }
 
-    let nullifier = read_num::<BigEndian, _, Fr>(r)?;
-    let out_commit = read_num::<BigEndian, _, Fr>(r)?;
-    let delta = read_num::<BigEndian, _, Fr>(r)?;
-    let proof = P::read::<BigEndian, _>(r)?;
-    let root_after = read_num::<BigEndian, _, Fr>(r)?;
-    let tree_proof = P::read::<BigEndian, _>(r)?;
+    Ok(())
+}
+
+fn read_args<R: Read, Fr: PrimeField, P: Proof>(
+    r: &mut R,
+    nums: &mut NumReader,
+) -> Result<TxData<Fr, P>> {
+    let nullifier = nums.num::<BigEndian, _, Fr>(r, TxField::Nullifier)?;
+    let out_commit = nums.num::<BigEndian, _, Fr>(r, TxField::OutCommit)?;
+    let delta = nums.num::<BigEndian, _, Fr>(r, TxField::Delta)?;
+    let proof = nums.proof::<BigEndian, _, P>(r, TxField::Proof)?;
+    let root_after = nums.num::<BigEndian, _, Fr>(r, TxField::RootAfter)?;
+    let tree_proof = nums.proof::<BigEndian, _, P>(r, TxField::TreeProof)?;
     let tx_type = r.read_u16::<BigEndian>()?;
     let memo_len = r.read_u16::<BigEndian>()?;
     let mut memo = vec![0u8; memo_len as usize];
@@ -44,12 +105,11 @@ pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P
         tx_type,
         memo,
         extra_data,
-        token_id: String::new(),
+        asset_id: AssetId::None,
     })
 }
 
-pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
-    w.write_all(SELECTOR)?;
+fn write_args<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
     write_num::<BigEndian, _, Fr>(w, &data.nullifier)?;
     write_num::<BigEndian, _, Fr>(w, &data.out_commit)?;
     write_num::<BigEndian, _, Fr>(w, &data.delta)?;
@@ -57,9 +117,482 @@ pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W
     write_num::<BigEndian, _, Fr>(w, &data.root_after)?;
     data.tree_proof.write::<BigEndian, _>(w)?;
     w.write_u16::<BigEndian>(data.tx_type as u16)?;
-    w.write_u16::<BigEndian>(data.memo.len() as u16)?;
+    let memo_len = u16::try_from(data.memo.len())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "memo too long"))?;
+    w.write_u16::<BigEndian>(memo_len)?;
     w.write_all(&data.memo)?;
     w.write_all(&data.extra_data)?;
 
     Ok(())
 }
+
+/// How an entry point carries the packed transaction.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EntryPointKind {
+    /// The packed transaction follows the selector.
+    Packed,
+    /// `multicall(bytes[])` batching calls to the pool itself, one of which is a packed call.
+    Multicall,
+}
+
+/// A pool contract function whose calldata carries the packed transaction.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EntryPoint {
+    pub signature: String,
+    pub selector: [u8; 4],
+    pub kind: EntryPointKind,
+}
+
+impl EntryPoint {
+    /// Entry point with the selector derived from a Solidity signature like `transact()`.
+    pub fn new(signature: &str) -> Self {
+        let hash = keccak256(signature.as_bytes());
+
+        Self {
+            signature: signature.to_owned(),
+            selector: [hash[0], hash[1], hash[2], hash[3]],
+            kind: EntryPointKind::Packed,
+        }
+    }
+
+    /// The pool's `transact()` entry point, using the deployed [`SELECTOR`].
+    pub fn transact() -> Self {
+        Self {
+            signature: "transact()".to_owned(),
+            selector: SELECTOR,
+            kind: EntryPointKind::Packed,
+        }
+    }
+
+    /// `transact()` with the selector derived from its signature, `0xaf989083`.
+    pub fn fork_transact() -> Self {
+        Self::new("transact()")
+    }
+
+    /// OpenZeppelin's `multicall(bytes[])`, `0xac9650d8`. Reads look for the packed call among
+    /// the batched ones.
+    pub fn multicall() -> Self {
+        Self {
+            kind: EntryPointKind::Multicall,
+            ..Self::new("multicall(bytes[])")
+        }
+    }
+
+    /// Entry points of the deployed pool contracts, all registered by [`EvmCodec::default`].
+    pub fn known() -> Vec<Self> {
+        vec![Self::transact(), Self::fork_transact(), Self::multicall()]
+    }
+}
+
+/// EVM codec for pool contracts with non-default or multiple entry points.
+///
+/// Writes use the first packed entry point. Reads accept any of them and report which packed
+/// entry point matched, the inner one for a multicall.
+#[derive(Debug, Clone)]
+pub struct EvmCodec {
+    entry_points: Vec<EntryPoint>,
+    token: Option<[u8; 20]>,
+}
+
+impl Default for EvmCodec {
+    fn default() -> Self {
+        Self {
+            entry_points: EntryPoint::known(),
+            token: None,
+        }
+    }
+}
+
+impl EvmCodec {
+    pub fn new(entry_point: EntryPoint) -> Self {
+        Self {
+            entry_points: vec![entry_point],
+            token: None,
+        }
+    }
+
+    /// Sets the ERC-20 token of the pool. Calldata has no asset field, so reads report it as
+    /// [`AssetId::Erc20`] and writes accept it besides [`AssetId::None`].
+    pub fn with_token(mut self, token: [u8; 20]) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    pub fn token(&self) -> Option<[u8; 20]> {
+        self.token
+    }
+
+    /// Additionally recognizes `entry_point` on read.
+    pub fn with_entry_point(mut self, entry_point: EntryPoint) -> Self {
+        self.entry_points.push(entry_point);
+        self
+    }
+
+    pub fn entry_points(&self) -> &[EntryPoint] {
+        &self.entry_points
+    }
+
+    pub fn read<R: Read, Fr: PrimeField, P: Proof>(
+        &self,
+        r: &mut R,
+    ) -> Result<(&EntryPoint, TxData<Fr, P>)> {
+        self.read_with_policy(r, NumPolicy::Strict)
+            .map(|(entry_point, data, _)| (entry_point, data))
+    }
+
+    pub fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+        &self,
+        r: &mut R,
+        policy: NumPolicy,
+    ) -> Result<(&EntryPoint, TxData<Fr, P>, NumReport)> {
+        let mut selector = [0u8; 4];
+        r.read_exact(&mut selector)?;
+        let entry_point = self.entry_point(selector)?;
+
+        let mut nums = NumReader::new(policy);
+        let (entry_point, mut data) = match entry_point.kind {
+            EntryPointKind::Packed => (entry_point, read_args(r, &mut nums)?),
+            EntryPointKind::Multicall => {
+                let mut args = vec![];
+                r.read_to_end(&mut args)?;
+                let (entry_point, mut call) = multicall_calls(&args)?
+                    .into_iter()
+                    .find_map(|call| {
+                        let entry_point = self.entry_point(call.get(..4)?.try_into().ok()?).ok()?;
+                        let packed = entry_point.kind == EntryPointKind::Packed;
+                        packed.then_some((entry_point, &call[4..]))
+                    })
+                    .ok_or_else(|| {
+                        Error::new(ErrorKind::InvalidData, "no pool call in multicall")
+                    })?;
+                (entry_point, read_args(&mut call, &mut nums)?)
+            }
+        };
+        if let Some(token) = self.token {
+            data.asset_id = AssetId::Erc20(token);
+        }
+
+        Ok((entry_point, data, nums.finish()?))
+    }
+
+    fn entry_point(&self, selector: [u8; 4]) -> Result<&EntryPoint> {
+        self.entry_points
+            .iter()
+            .find(|entry_point| entry_point.selector == selector)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid selector"))
+    }
+
+    pub fn write<W: Write, Fr: PrimeField, P: Proof>(
+        &self,
+        data: &TxData<Fr, P>,
+        w: &mut W,
+    ) -> Result<()> {
+        match (&data.asset_id, self.token) {
+            (AssetId::Erc20(token), Some(pool_token)) if *token == pool_token => {}
+            (asset_id, _) => asset_id.ensure_none()?,
+        }
+
+        let entry_point = self
+            .entry_points
+            .iter()
+            .find(|entry_point| entry_point.kind == EntryPointKind::Packed)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "no packed entry point"))?;
+
+        w.write_all(&entry_point.selector)?;
+        write_args(data, w)
+    }
+}
+
+/// Calls batched by the ABI encoded `bytes[]` argument of a multicall: the offset of the array,
+/// its length, then the offset of each call relative to the first of them and the length of
+/// its bytes.
+fn multicall_calls(args: &[u8]) -> Result<Vec<&[u8]>> {
+    let array = abi_word(args, 0)?;
+    let len = abi_word(args, array)?;
+    let items = array + 32;
+
+    (0..len)
+        .map(|i| {
+            let slot = i
+                .checked_mul(32)
+                .and_then(|offset| offset.checked_add(items))
+                .ok_or_else(|| invalid_multicall("call offset out of range"))?;
+            let call = abi_word(args, slot)?
+                .checked_add(items)
+                .ok_or_else(|| invalid_multicall("call out of range"))?;
+            let call_len = abi_word(args, call)?;
+
+            args[call + 32..]
+                .get(..call_len)
+                .ok_or_else(|| invalid_multicall("call out of range"))
+        })
+        .collect()
+}
+
+/// The `uint256` word at `at`, which must fit into `usize`.
+fn abi_word(args: &[u8], at: usize) -> Result<usize> {
+    let word = at
+        .checked_add(32)
+        .and_then(|end| args.get(at..end))
+        .ok_or_else(|| invalid_multicall("word out of range"))?;
+    let (high, low) = word.split_at(24);
+    if high.iter().any(|&b| b != 0) {
+        return Err(invalid_multicall("word out of range"));
+    }
+
+    usize::try_from(u64::from_be_bytes(low.try_into().unwrap()))
+        .map_err(|_| invalid_multicall("word out of range"))
+}
+
+fn invalid_multicall(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("invalid multicall: {msg}"))
+}
+
+/// EVM calldata for the pool `transact()` call. Hashed with keccak256.
+pub struct Evm;
+
+impl Codec for Evm {
+    type MemoOrder = BigEndian;
+
+    fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
+        read(r)
+    }
+
+    fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+        r: &mut R,
+        policy: NumPolicy,
+    ) -> Result<(TxData<Fr, P>, NumReport)> {
+        read_with_policy(r, policy)
+    }
+
+    fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+        write(data, w)
+    }
+
+    fn read_withdraw_address<R: Read>(r: &mut R) -> Result<ChainAddress> {
+        let mut address = [0u8; 20];
+        r.read_exact(&mut address)?;
+        Ok(ChainAddress::Evm(address))
+    }
+
+    fn write_withdraw_address<W: Write>(address: &ChainAddress, w: &mut W) -> Result<()> {
+        match address {
+            ChainAddress::Evm(address) => w.write_all(address),
+            _ => Err(unexpected_address(address)),
+        }
+    }
+
+    fn hash(bytes: &[u8]) -> [u8; 32] {
+        keccak256(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fawkes_crypto::ff_uint::Num;
+    use proptest::{collection::vec, prelude::*, sample::Index};
+
+    use super::*;
+    use crate::utils::tests::{
+        arb_tx_data, check_roundtrip, check_truncated, sample_tx, TestFr, TestProof, TestTxData,
+    };
+
+    #[test]
+    fn test_evm_codec_entry_points() {
+        let data = TestTxData {
+            memo: vec![1, 2, 3],
+            extra_data: vec![4; 64],
+            ..sample_tx(TxType::Deposit)
+        };
+
+        let direct = EvmCodec::new(EntryPoint::new("transactDirect()"));
+        let mut buf = vec![];
+        direct.write(&data, &mut buf).unwrap();
+
+        let codec = EvmCodec::default().with_entry_point(EntryPoint::new("transactDirect()"));
+        let (entry_point, data2) = codec.read(&mut &buf[..]).unwrap();
+        assert_eq!(entry_point.signature, "transactDirect()");
+        assert_eq!(data, data2);
+
+        let result: Result<TestTxData> = read(&mut &buf[..]);
+        assert!(result.is_err());
+
+        let fork = EvmCodec::new(EntryPoint::fork_transact());
+        let mut buf = vec![];
+        fork.write(&data, &mut buf).unwrap();
+        assert_eq!(buf[..4], [0xaf, 0x98, 0x90, 0x83]);
+        let codec = EvmCodec::default();
+        let (entry_point, _) = codec.read::<_, TestFr, TestProof>(&mut &buf[..]).unwrap();
+        assert_eq!(entry_point, &EntryPoint::fork_transact());
+    }
+
+    #[test]
+    fn test_evm_codec_token() {
+        let usdc = EvmCodec::default().with_token([0xa0; 20]);
+        let mut data = TestTxData {
+            memo: vec![1, 2, 3],
+            asset_id: AssetId::Erc20([0xa0; 20]),
+            ..sample_tx(TxType::Transfer)
+        };
+
+        let mut buf = vec![];
+        usdc.write(&data, &mut buf).unwrap();
+        let (_, decoded) = usdc.read(&mut &buf[..]).unwrap();
+        assert_eq!(decoded, data);
+
+        let native: TestTxData = read(&mut &buf[..]).unwrap();
+        assert_eq!(native.asset_id, AssetId::None);
+        assert!(EvmCodec::default().write(&data, &mut vec![]).is_err());
+
+        data.asset_id = AssetId::Erc20([0xb0; 20]);
+        assert!(usdc.write(&data, &mut vec![]).is_err());
+    }
+
+    /// ABI encodes `multicall(bytes[])` calldata.
+    fn multicall(calls: &[&[u8]]) -> Vec<u8> {
+        let word = |n: usize| {
+            let mut word = [0u8; 32];
+            word[24..].copy_from_slice(&(n as u64).to_be_bytes());
+            word
+        };
+
+        let mut heads = vec![];
+        let mut tails = vec![];
+        for call in calls {
+            heads.extend_from_slice(&word(calls.len() * 32 + tails.len()));
+            tails.extend_from_slice(&word(call.len()));
+            tails.extend_from_slice(call);
+            tails.resize(tails.len().next_multiple_of(32), 0);
+        }
+
+        let mut buf = EntryPoint::multicall().selector.to_vec();
+        buf.extend_from_slice(&word(32));
+        buf.extend_from_slice(&word(calls.len()));
+        buf.extend_from_slice(&heads);
+        buf.extend_from_slice(&tails);
+        buf
+    }
+
+    #[test]
+    fn test_evm_known_selectors() {
+        assert_eq!(
+            EntryPoint::fork_transact().selector,
+            [0xaf, 0x98, 0x90, 0x83]
+        );
+        assert_eq!(EntryPoint::multicall().selector, [0xac, 0x96, 0x50, 0xd8]);
+        assert_eq!(EntryPoint::multicall().kind, EntryPointKind::Multicall);
+    }
+
+    #[test]
+    fn test_evm_multicall() {
+        let data = TestTxData {
+            nullifier: Num::ONE,
+            memo: vec![1, 2, 3],
+            extra_data: vec![4; 5],
+            ..sample_tx(TxType::Transfer)
+        };
+        let mut transact = vec![];
+        EvmCodec::new(EntryPoint::fork_transact())
+            .write(&data, &mut transact)
+            .unwrap();
+        let permit = [0xd5, 0x05, 0xac, 0xcf, 1, 2, 3];
+
+        let buf = multicall(&[&permit, &transact]);
+        let codec = EvmCodec::default();
+        let (entry_point, data2) = codec.read(&mut &buf[..]).unwrap();
+        assert_eq!(entry_point, &EntryPoint::fork_transact());
+        assert_eq!(data, data2);
+
+        let codec = EvmCodec::new(EntryPoint::multicall()).with_entry_point(EntryPoint::transact());
+        let mut buf = vec![];
+        codec.write(&data, &mut buf).unwrap();
+        assert_eq!(buf[..4], SELECTOR);
+
+        let buf = multicall(&[&permit]);
+        let err = EvmCodec::default()
+            .read::<_, TestFr, TestProof>(&mut &buf[..])
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no pool call in multicall");
+    }
+
+    #[test]
+    fn test_evm_multicall_envelope() {
+        let tx = sample_tx(TxType::Transfer);
+        let mut transact = vec![];
+        EvmCodec::new(EntryPoint::fork_transact())
+            .write(&tx, &mut transact)
+            .unwrap();
+        let buf = multicall(&[&transact]);
+        let read = |buf: &[u8]| {
+            EvmCodec::default()
+                .read::<_, TestFr, TestProof>(&mut &buf[..])
+                .map(|_| ())
+                .map_err(|err| err.kind())
+        };
+        let set_word = |at: usize, value: u64| {
+            let mut buf = buf.clone();
+            buf[4 + at..4 + at + 32].fill(0);
+            buf[4 + at + 24..4 + at + 32].copy_from_slice(&value.to_be_bytes());
+            buf
+        };
+        assert_eq!(read(&buf), Ok(()));
+
+        let mut huge_offset = buf.clone();
+        huge_offset[4] = 1;
+        let corrupted = [
+            // array offset
+            huge_offset,
+            set_word(0, buf.len() as u64),
+            // array length
+            set_word(32, buf.len() as u64),
+            set_word(32, u64::MAX),
+            // call offset
+            set_word(64, buf.len() as u64),
+            set_word(64, u64::MAX),
+            // call length
+            set_word(96, transact.len() as u64 + 32),
+            set_word(96, u64::MAX),
+        ];
+        for buf in corrupted {
+            assert_eq!(read(&buf), Err(ErrorKind::InvalidData));
+        }
+        assert_eq!(
+            read(&set_word(96, transact.len() as u64 - 1)),
+            Err(ErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn test_evm_memo_too_long() {
+        let data = TestTxData {
+            memo: vec![0; u16::MAX as usize + 1],
+            ..sample_tx(TxType::Transfer)
+        };
+        let err = write(&data, &mut vec![]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    fn asset_id() -> BoxedStrategy<AssetId> {
+        Just(AssetId::None).boxed()
+    }
+
+    fn extra_data(_: TxType) -> BoxedStrategy<Vec<u8>> {
+        vec(any::<u8>(), 0..128).boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn test_evm_roundtrip(
+            tx in arb_tx_data::<TestProof>(asset_id, extra_data),
+            len in any::<Index>(),
+        ) {
+            check_roundtrip::<Evm, _>(&tx)?;
+            check_truncated::<Evm, _>(&tx, len)?;
+        }
+
+        #[test]
+        fn test_evm_read_random_bytes(bytes in vec(any::<u8>(), 0..1024)) {
+            let _ = read::<_, TestFr, TestProof>(&mut &bytes[..]);
+        }
+    }
+}
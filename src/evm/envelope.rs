@@ -0,0 +1,380 @@
+use std::io::{Error, ErrorKind, Result};
+
+use fawkes_crypto::ff_uint::PrimeField;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use rlp::{Rlp, RlpStream};
+
+use super::{EntryPoint, EvmCodec};
+use crate::{
+    hash::keccak256,
+    policy::{NumPolicy, NumReport},
+    proof::Proof,
+    TxData,
+};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EnvelopeType {
+    Legacy,
+    /// EIP-2930 transaction with an access list.
+    AccessList,
+    /// EIP-1559 transaction with a priority fee.
+    DynamicFee,
+}
+
+/// Fields of a raw Ethereum transaction relevant for indexing.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EvmTransaction {
+    pub envelope_type: EnvelopeType,
+    /// `None` for pre-EIP-155 legacy transactions.
+    pub chain_id: Option<u64>,
+    /// `None` for contract creations.
+    pub to: Option<[u8; 20]>,
+    pub input: Vec<u8>,
+    /// Sender recovered from the signature, `None` if the transaction is unsigned.
+    pub from: Option<[u8; 20]>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum NotPoolTransaction {
+    ContractCreation,
+    OtherContract([u8; 20]),
+    UnknownSelector,
+}
+
+pub enum PoolTransaction<'a, Fr: PrimeField, P: Proof> {
+    Pool {
+        transaction: EvmTransaction,
+        entry_point: &'a EntryPoint,
+        tx: TxData<Fr, P>,
+    },
+    NotPool(EvmTransaction, NotPoolTransaction),
+}
+
+impl EvmTransaction {
+    /// Decodes a raw transaction as returned by `eth_getRawTransactionByHash`.
+    pub fn decode(raw: &[u8]) -> Result<Self> {
+        match raw.first() {
+            Some(0x01) => decode_typed(EnvelopeType::AccessList, &raw[1..]),
+            Some(0x02) => decode_typed(EnvelopeType::DynamicFee, &raw[1..]),
+            Some(0xc0..) => decode_legacy(raw),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                "unsupported transaction type",
+            )),
+        }
+    }
+}
+
+/// Decodes a raw transaction and, if it calls one of the `codec` entry points of the pool at
+/// `pool_address`, the ZeroPool transaction it carries.
+pub fn decode_pool_transaction<'a, Fr: PrimeField, P: Proof>(
+    raw: &[u8],
+    pool_address: [u8; 20],
+    codec: &'a EvmCodec,
+) -> Result<PoolTransaction<'a, Fr, P>> {
+    decode_pool_transaction_with_policy(raw, pool_address, codec, NumPolicy::Strict)
+        .map(|(transaction, _)| transaction)
+}
+
+/// [`decode_pool_transaction`] with non-canonical field elements handled according to `policy`.
+/// The report is empty for non-pool transactions.
+pub fn decode_pool_transaction_with_policy<'a, Fr: PrimeField, P: Proof>(
+    raw: &[u8],
+    pool_address: [u8; 20],
+    codec: &'a EvmCodec,
+    policy: NumPolicy,
+) -> Result<(PoolTransaction<'a, Fr, P>, NumReport)> {
+    let transaction = EvmTransaction::decode(raw)?;
+
+    let reason = match transaction.to {
+        None => Some(NotPoolTransaction::ContractCreation),
+        Some(to) if to != pool_address => Some(NotPoolTransaction::OtherContract(to)),
+        _ => None,
+    };
+    if let Some(reason) = reason {
+        return Ok((
+            PoolTransaction::NotPool(transaction, reason),
+            NumReport::default(),
+        ));
+    }
+
+    let known_selector = transaction.input.get(..4).is_some_and(|selector| {
+        codec
+            .entry_points()
+            .iter()
+            .any(|entry_point| entry_point.selector == selector)
+    });
+    if !known_selector {
+        return Ok((
+            PoolTransaction::NotPool(transaction, NotPoolTransaction::UnknownSelector),
+            NumReport::default(),
+        ));
+    }
+
+    let (entry_point, tx, report) = codec.read_with_policy(&mut &transaction.input[..], policy)?;
+
+    Ok((
+        PoolTransaction::Pool {
+            transaction,
+            entry_point,
+            tx,
+        },
+        report,
+    ))
+}
+
+fn decode_legacy(raw: &[u8]) -> Result<EvmTransaction> {
+    // nonce, gasPrice, gas, to, value, data[, v, r, s]
+    const UNSIGNED_FIELDS: usize = 6;
+
+    let rlp = Rlp::new(raw);
+    let fields = list_len(&rlp, UNSIGNED_FIELDS)?;
+
+    let mut transaction = EvmTransaction {
+        envelope_type: EnvelopeType::Legacy,
+        chain_id: None,
+        to: decode_to(&rlp.at(3).map_err(invalid_rlp)?)?,
+        input: rlp.val_at(5).map_err(invalid_rlp)?,
+        from: None,
+    };
+
+    if fields == UNSIGNED_FIELDS {
+        return Ok(transaction);
+    }
+
+    let v: u64 = rlp.val_at(6).map_err(invalid_rlp)?;
+    let mut stream = RlpStream::new_list(if v >= 35 { 9 } else { 6 });
+    for i in 0..UNSIGNED_FIELDS {
+        stream.append_raw(rlp.at(i).map_err(invalid_rlp)?.as_raw(), 1);
+    }
+
+    let y_parity = if v >= 35 {
+        let chain_id = (v - 35) / 2;
+        stream.append(&chain_id).append(&0u8).append(&0u8);
+        transaction.chain_id = Some(chain_id);
+        (v - 35) % 2
+    } else {
+        v.checked_sub(27)
+            .filter(|y_parity| *y_parity <= 1)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid signature v"))?
+    };
+
+    let sighash = keccak256(&stream.out());
+    transaction.from = Some(recover(&rlp, 7, y_parity, &sighash)?);
+
+    Ok(transaction)
+}
+
+fn decode_typed(envelope_type: EnvelopeType, payload: &[u8]) -> Result<EvmTransaction> {
+    // EIP-2930: chainId, nonce, gasPrice, gas, to, value, data, accessList[, yParity, r, s]
+    // EIP-1559: chainId, nonce, maxPriorityFee, maxFee, gas, to, value, data, accessList[, ...]
+    let (prefix, unsigned_fields) = match envelope_type {
+        EnvelopeType::AccessList => (0x01, 8),
+        EnvelopeType::DynamicFee => (0x02, 9),
+        EnvelopeType::Legacy => unreachable!(),
+    };
+    let to_index = unsigned_fields - 4;
+
+    let rlp = Rlp::new(payload);
+    let fields = list_len(&rlp, unsigned_fields)?;
+
+    let mut transaction = EvmTransaction {
+        envelope_type,
+        chain_id: Some(rlp.val_at(0).map_err(invalid_rlp)?),
+        to: decode_to(&rlp.at(to_index).map_err(invalid_rlp)?)?,
+        input: rlp.val_at(to_index + 2).map_err(invalid_rlp)?,
+        from: None,
+    };
+
+    if fields == unsigned_fields {
+        return Ok(transaction);
+    }
+
+    let mut stream = RlpStream::new_list(unsigned_fields);
+    for i in 0..unsigned_fields {
+        stream.append_raw(rlp.at(i).map_err(invalid_rlp)?.as_raw(), 1);
+    }
+    let sighash = keccak256(&[&[prefix][..], &stream.out()].concat());
+
+    let y_parity: u64 = rlp.val_at(unsigned_fields).map_err(invalid_rlp)?;
+    transaction.from = Some(recover(&rlp, unsigned_fields + 1, y_parity, &sighash)?);
+
+    Ok(transaction)
+}
+
+/// Returns the number of list items, which must be either `unsigned_fields` or
+/// `unsigned_fields + 3` for a signed transaction.
+fn list_len(rlp: &Rlp, unsigned_fields: usize) -> Result<usize> {
+    let fields = rlp.item_count().map_err(invalid_rlp)?;
+
+    if fields != unsigned_fields && fields != unsigned_fields + 3 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "unexpected number of transaction fields",
+        ));
+    }
+
+    Ok(fields)
+}
+
+fn decode_to(rlp: &Rlp) -> Result<Option<[u8; 20]>> {
+    let to = rlp.data().map_err(invalid_rlp)?;
+
+    if to.is_empty() {
+        return Ok(None);
+    }
+
+    to.try_into()
+        .map(Some)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid recipient address"))
+}
+
+fn recover(rlp: &Rlp, r_index: usize, y_parity: u64, sighash: &[u8; 32]) -> Result<[u8; 20]> {
+    let invalid_signature = || Error::new(ErrorKind::InvalidData, "invalid signature");
+
+    let mut signature = [0u8; 64];
+    for (i, index) in [r_index, r_index + 1].into_iter().enumerate() {
+        let scalar = rlp.at(index).map_err(invalid_rlp)?;
+        let scalar = scalar.data().map_err(invalid_rlp)?;
+        if scalar.len() > 32 {
+            return Err(invalid_signature());
+        }
+        signature[i * 32 + 32 - scalar.len()..(i + 1) * 32].copy_from_slice(scalar);
+    }
+
+    let signature = Signature::from_slice(&signature).map_err(|_| invalid_signature())?;
+    let recovery_id = u8::try_from(y_parity)
+        .ok()
+        .filter(|y_parity| *y_parity <= 1)
+        .and_then(RecoveryId::from_byte)
+        .ok_or_else(invalid_signature)?;
+    let key = VerifyingKey::recover_from_prehash(sighash, &signature, recovery_id)
+        .map_err(|_| invalid_signature())?;
+
+    let public_key = key.to_encoded_point(false);
+    let hash = keccak256(&public_key.as_bytes()[1..]);
+
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    Ok(address)
+}
+
+fn invalid_rlp(err: rlp::DecoderError) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("invalid rlp: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use fawkes_crypto::ff_uint::Num;
+    use k256::ecdsa::SigningKey;
+
+    use super::*;
+    use crate::{
+        utils::tests::{sample_tx, TestFr, TestProof, TestTxData},
+        TxType,
+    };
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_eip155_legacy() {
+        // Example transaction from EIP-155.
+        let raw = from_hex(concat!(
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000",
+            "8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f",
+            "761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        ));
+
+        let transaction = EvmTransaction::decode(&raw).unwrap();
+        assert_eq!(transaction.envelope_type, EnvelopeType::Legacy);
+        assert_eq!(transaction.chain_id, Some(1));
+        assert_eq!(transaction.to, Some([0x35; 20]));
+        assert!(transaction.input.is_empty());
+        assert_eq!(
+            transaction.from.unwrap()[..],
+            from_hex("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f")
+        );
+
+        let codec = EvmCodec::default();
+        let decoded =
+            decode_pool_transaction::<TestFr, TestProof>(&raw, [0x11; 20], &codec).unwrap();
+        let PoolTransaction::NotPool(_, reason) = decoded else {
+            panic!("expected a non-pool transaction");
+        };
+        assert_eq!(reason, NotPoolTransaction::OtherContract([0x35; 20]));
+    }
+
+    #[test]
+    fn test_decode_eip1559_pool_transaction() {
+        let data = TestTxData {
+            nullifier: Num::from(1u64),
+            memo: vec![1, 2, 3],
+            ..sample_tx(TxType::Transfer)
+        };
+        let mut input = vec![];
+        super::super::write(&data, &mut input).unwrap();
+
+        let pool = [0x22; 20];
+        let mut stream = RlpStream::new_list(9);
+        stream
+            .append(&5u64)
+            .append(&7u64)
+            .append(&1_000_000_000u64)
+            .append(&2_000_000_000u64)
+            .append(&500_000u64)
+            .append(&&pool[..])
+            .append(&0u8)
+            .append(&input)
+            .begin_list(0);
+        let payload = [&[0x02][..], &stream.out()].concat();
+
+        let key = SigningKey::from_slice(&[0x46; 32]).unwrap();
+        let (signature, recovery_id) = key.sign_prehash_recoverable(&keccak256(&payload)).unwrap();
+        let (r, s) = signature.split_bytes();
+
+        let mut stream = RlpStream::new_list(12);
+        let unsigned = Rlp::new(&payload[1..]);
+        for i in 0..9 {
+            stream.append_raw(unsigned.at(i).unwrap().as_raw(), 1);
+        }
+        stream
+            .append(&recovery_id.to_byte())
+            .append(&r.as_slice())
+            .append(&s.as_slice());
+        let raw = [&[0x02][..], &stream.out()].concat();
+
+        let codec = EvmCodec::default();
+        let decoded = decode_pool_transaction(&raw, pool, &codec).unwrap();
+        let PoolTransaction::Pool {
+            transaction,
+            entry_point,
+            tx,
+        } = decoded
+        else {
+            panic!("expected a pool transaction");
+        };
+
+        assert_eq!(transaction.envelope_type, EnvelopeType::DynamicFee);
+        assert_eq!(transaction.chain_id, Some(5));
+        assert_eq!(
+            transaction.from.unwrap()[..],
+            from_hex("9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f")
+        );
+        assert_eq!(entry_point, &EntryPoint::transact());
+        assert_eq!(tx, data);
+
+        let (_, report) = decode_pool_transaction_with_policy::<TestFr, TestProof>(
+            &raw,
+            pool,
+            &codec,
+            NumPolicy::Reject,
+        )
+        .unwrap();
+        assert!(report.is_canonical());
+    }
+}
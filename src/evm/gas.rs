@@ -0,0 +1,183 @@
+use std::io::{Result, Write};
+
+use fawkes_crypto::ff_uint::PrimeField;
+
+use crate::{proof::Proof, TxData};
+
+pub const ZERO_BYTE_GAS: u64 = 4;
+pub const NON_ZERO_BYTE_GAS: u64 = 16;
+/// Gas `GasPriceOracle` adds for the 68 byte signature missing from an unsigned transaction.
+pub const OPTIMISM_SIGNATURE_GAS: u64 = 68 * NON_ZERO_BYTE_GAS;
+
+/// Byte counts of encoded calldata, which is all L1 and L2 data pricing depends on.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct CalldataStats {
+    pub zero_bytes: u64,
+    pub non_zero_bytes: u64,
+}
+
+impl CalldataStats {
+    /// Fails if `data` cannot be encoded, e.g. when it carries an asset id.
+    pub fn of<Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>) -> Result<Self> {
+        let mut stats = Self::default();
+        super::write(data, &mut stats)?;
+        Ok(stats)
+    }
+
+    pub fn len(&self) -> u64 {
+        self.zero_bytes + self.non_zero_bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Intrinsic calldata gas as charged on L1.
+    pub fn gas(&self) -> u64 {
+        self.zero_bytes * ZERO_BYTE_GAS + self.non_zero_bytes * NON_ZERO_BYTE_GAS
+    }
+}
+
+impl Write for CalldataStats {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let zero_bytes = buf.iter().filter(|byte| **byte == 0).count() as u64;
+        self.zero_bytes += zero_bytes;
+        self.non_zero_bytes += buf.len() as u64 - zero_bytes;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Intrinsic calldata gas of the encoded transaction, computed without allocating it.
+pub fn calldata_gas<Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>) -> Result<u64> {
+    Ok(CalldataStats::of(data)?.gas())
+}
+
+/// Fee a rollup charges for publishing calldata to L1, in wei.
+pub trait L2DataFeeModel {
+    fn data_fee(&self, stats: &CalldataStats) -> u128;
+}
+
+/// OP Stack pricing since the Ecotone upgrade, matching `GasPriceOracle.getL1Fee`: calldata gas
+/// plus [`OPTIMISM_SIGNATURE_GAS`], at a price weighted by the scalars, which have 6 decimals.
+/// Only calldata is priced, not the other fields of the RLP encoded transaction. Saturates at
+/// `u128::MAX`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct OptimismEcotone {
+    pub l1_base_fee: u128,
+    pub base_fee_scalar: u128,
+    pub blob_base_fee: u128,
+    pub blob_base_fee_scalar: u128,
+}
+
+impl L2DataFeeModel for OptimismEcotone {
+    fn data_fee(&self, stats: &CalldataStats) -> u128 {
+        let weighted_gas_price = self
+            .base_fee_scalar
+            .saturating_mul(self.l1_base_fee)
+            .saturating_mul(16)
+            .saturating_add(self.blob_base_fee_scalar.saturating_mul(self.blob_base_fee));
+
+        let l1_gas_used = stats.gas() as u128 + OPTIMISM_SIGNATURE_GAS as u128;
+        l1_gas_used.saturating_mul(weighted_gas_price) / (16 * 1_000_000)
+    }
+}
+
+/// Arbitrum L1 pricing: every calldata byte is charged L1 gas units at the price reported by
+/// `ArbGasInfo.getL1BaseFeeEstimate`, with `padding_units` covering the batch overhead. Saturates
+/// at `u128::MAX`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Arbitrum {
+    pub l1_price_per_unit: u128,
+    pub padding_units: u64,
+}
+
+impl L2DataFeeModel for Arbitrum {
+    fn data_fee(&self, stats: &CalldataStats) -> u128 {
+        (stats.gas() as u128 + self.padding_units as u128).saturating_mul(self.l1_price_per_unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fawkes_crypto::ff_uint::Num;
+
+    use super::*;
+    use crate::{
+        utils::tests::{sample_tx, TestTxData},
+        AssetId, TxType,
+    };
+
+    #[test]
+    fn test_calldata_gas_matches_encoding() {
+        let data = TestTxData {
+            root_after: Num::from(4u64),
+            delta: Num::from(3u64),
+            out_commit: Num::from(2u64),
+            nullifier: Num::from(1u64),
+            memo: vec![0, 1, 2, 0],
+            ..sample_tx(TxType::Transfer)
+        };
+
+        let mut buf = vec![];
+        super::super::write(&data, &mut buf).unwrap();
+        let zero_bytes = buf.iter().filter(|byte| **byte == 0).count() as u64;
+        let non_zero_bytes = buf.len() as u64 - zero_bytes;
+
+        assert_eq!(
+            calldata_gas(&data).unwrap(),
+            zero_bytes * 4 + non_zero_bytes * 16
+        );
+
+        let stats = CalldataStats::of(&data).unwrap();
+        let arbitrum = Arbitrum {
+            l1_price_per_unit: 10,
+            padding_units: 0,
+        };
+        assert_eq!(arbitrum.data_fee(&stats), stats.gas() as u128 * 10);
+
+        let optimism = OptimismEcotone {
+            l1_base_fee: 1_000_000,
+            base_fee_scalar: 1_000_000,
+            blob_base_fee: 0,
+            blob_base_fee_scalar: 0,
+        };
+        assert_eq!(
+            optimism.data_fee(&stats),
+            (stats.gas() + 68 * 16) as u128 * 1_000_000
+        );
+
+        let optimism = OptimismEcotone {
+            l1_base_fee: u128::MAX,
+            ..optimism
+        };
+        assert_eq!(optimism.data_fee(&stats), u128::MAX / (16 * 1_000_000));
+
+        let data = TestTxData {
+            asset_id: AssetId::Id32([1; 32]),
+            ..data
+        };
+        assert!(calldata_gas(&data).is_err());
+    }
+
+    #[test]
+    fn test_optimism_ecotone_l1_fee() {
+        // `getL1Fee` for 100 zero and 200 non-zero bytes at 10 gwei L1 base fee, 1 wei blob base
+        // fee and OP Mainnet's Ecotone scalars.
+        let optimism = OptimismEcotone {
+            l1_base_fee: 10_000_000_000,
+            base_fee_scalar: 1368,
+            blob_base_fee: 1,
+            blob_base_fee_scalar: 810_949,
+        };
+        let stats = CalldataStats {
+            zero_bytes: 100,
+            non_zero_bytes: 200,
+        };
+
+        assert_eq!(optimism.data_fee(&stats), 64_131_840_237);
+    }
+}
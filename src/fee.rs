@@ -0,0 +1,133 @@
+use std::{
+    fmt::{Display, Formatter},
+    io::{Result, Write},
+};
+
+use byteorder::BigEndian;
+use fawkes_crypto::ff_uint::PrimeField;
+
+use crate::{proof::Proof, Codec, TxData, TxType};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FeeRejection {
+    /// The memo is too short to contain a fee.
+    MissingFee,
+    /// The committed fee is below what the policy requires.
+    Insufficient { fee: u64, min_fee: u64 },
+}
+
+impl Display for FeeRejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeeRejection::MissingFee => write!(f, "memo does not contain a fee"),
+            FeeRejection::Insufficient { fee, min_fee } => {
+                write!(f, "fee {fee} is below the minimum of {min_fee}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FeeRejection {}
+
+/// Minimum fee a relayer accepts for a transaction.
+pub trait FeePolicy<Fr: PrimeField, P: Proof> {
+    fn min_fee(&self, tx: &TxData<Fr, P>) -> u64;
+
+    /// Checks the fee committed to in the memo, returning it if it's sufficient.
+    fn check<C: Codec>(&self, tx: &TxData<Fr, P>) -> std::result::Result<u64, FeeRejection> {
+        let fee = tx.fee::<C>().map_err(|_| FeeRejection::MissingFee)?;
+        let min_fee = self.min_fee(tx);
+
+        if fee < min_fee {
+            return Err(FeeRejection::Insufficient { fee, min_fee });
+        }
+
+        Ok(fee)
+    }
+}
+
+/// A base fee per tx type plus a price per memo byte and per encoded proof byte.
+///
+/// Both proofs are counted, so Plonk proofs, which are larger than Groth16 ones, cost more under
+/// the same policy.
+#[derive(Debug, Clone, Default)]
+pub struct LinearFeePolicy {
+    pub deposit: u64,
+    pub transfer: u64,
+    pub withdraw: u64,
+    pub per_memo_byte: u64,
+    pub per_proof_byte: u64,
+}
+
+impl<Fr: PrimeField, P: Proof> FeePolicy<Fr, P> for LinearFeePolicy {
+    fn min_fee(&self, tx: &TxData<Fr, P>) -> u64 {
+        let base = match tx.tx_type {
+            TxType::Deposit => self.deposit,
+            TxType::Transfer => self.transfer,
+            TxType::Withdraw => self.withdraw,
+        };
+        let proof_len = proof_len(&tx.proof) + proof_len(&tx.tree_proof);
+
+        base.saturating_add(self.per_memo_byte.saturating_mul(tx.memo.len() as u64))
+            .saturating_add(self.per_proof_byte.saturating_mul(proof_len))
+    }
+}
+
+fn proof_len<P: Proof>(proof: &P) -> u64 {
+    struct Counter(u64);
+
+    impl Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.0 += buf.len() as u64;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    // Byte order doesn't affect the length.
+    let _ = proof.write::<BigEndian, _>(&mut counter);
+    counter.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        evm::Evm,
+        near::Near,
+        utils::tests::{sample_tx, TestTxData},
+    };
+
+    #[test]
+    fn test_linear_fee_policy() {
+        let mut tx = TestTxData {
+            memo: [&100u64.to_be_bytes()[..], &[0; 28]].concat(),
+            ..sample_tx(TxType::Withdraw)
+        };
+        let policy = LinearFeePolicy {
+            withdraw: 20,
+            per_memo_byte: 1,
+            per_proof_byte: 0,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.check::<Evm>(&tx), Ok(100));
+        assert_eq!(tx.fee::<Near>().unwrap(), 100 << 56);
+
+        tx.memo[7] = 10;
+        assert_eq!(
+            policy.check::<Evm>(&tx),
+            Err(FeeRejection::Insufficient {
+                fee: 10,
+                min_fee: 56
+            })
+        );
+
+        tx.memo.truncate(4);
+        assert_eq!(policy.check::<Evm>(&tx), Err(FeeRejection::MissingFee));
+    }
+}
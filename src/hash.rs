@@ -0,0 +1,15 @@
+use blake2::{digest::consts::U32, Blake2b};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+pub fn keccak256(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes).into()
+}
+
+pub fn blake2b256(bytes: &[u8]) -> [u8; 32] {
+    Blake2b::<U32>::digest(bytes).into()
+}
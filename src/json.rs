@@ -0,0 +1,517 @@
+//! Opt-in JSON representation of Groth16 transactions for HTTP APIs, described by
+//! `schema/tx.schema.json`.
+//!
+//! Field elements are `0x`-prefixed 32-byte big-endian hex, tx types are `"deposit"`,
+//! `"transfer"` and `"withdraw"`, and byte strings are `0x`-prefixed hex or `base64:`-prefixed
+//! base64. Unlike the serde representation of [`TxData`], it also carries the decoded delta and
+//! memo. Those are derived from the other fields and ignored when reading, so converting back is
+//! lossless.
+
+use std::{
+    fmt::{Display, Formatter},
+    io::{Error, ErrorKind, Result},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use fawkes_crypto::{
+    backend::bellman_groth16::{
+        engines::Engine,
+        group::{G1Point, G2Point},
+        prover::Proof,
+    },
+    ff_uint::{Num, NumRepr, PrimeField, Uint},
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    delta::Delta, memo::MemoLayout, withdraw::WithdrawData, AssetId, Codec, TxData, TxType,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct TxJson<Fr: PrimeField, Fq: PrimeField> {
+    #[serde(with = "symbolic_tx_type")]
+    pub tx_type: TxType,
+    pub nullifier: HexNum<Fr>,
+    pub out_commit: HexNum<Fr>,
+    pub root_after: HexNum<Fr>,
+    pub delta: HexNum<Fr>,
+    pub proof: ProofJson<Fq>,
+    pub tree_proof: ProofJson<Fq>,
+    pub memo: Bytes,
+    pub extra_data: Bytes,
+    #[serde(default, skip_serializing_if = "AssetIdJson::is_none")]
+    pub asset_id: AssetIdJson,
+    /// `None` if the delta has bits set above the pool id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta_decoded: Option<DeltaJson>,
+    /// `None` if the memo doesn't parse with the chain's byte order and the default layout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo_decoded: Option<MemoJson<Fr>>,
+}
+
+/// Groth16 proof with every coordinate as a field element of the base field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ProofJson<Fq: PrimeField> {
+    pub a: [HexNum<Fq>; 2],
+    pub b: [[HexNum<Fq>; 2]; 2],
+    pub c: [HexNum<Fq>; 2],
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetIdJson {
+    #[default]
+    None,
+    Id32(Bytes),
+    AccountId(String),
+    Erc20(Bytes),
+}
+
+/// Amounts are decimal strings, since JSON numbers lose precision above 2^53.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeltaJson {
+    #[serde(with = "crate::utils::serde_string")]
+    pub token_amount: i64,
+    #[serde(with = "crate::utils::serde_string")]
+    pub energy_amount: i128,
+    pub index: u64,
+    pub pool_id: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct MemoJson<Fr: PrimeField> {
+    #[serde(with = "crate::utils::serde_string")]
+    pub fee: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdraw: Option<WithdrawJson>,
+    pub ephemeral_key: Bytes,
+    pub shared_secret_ciphertext: Bytes,
+    /// The account followed by the output notes.
+    pub items: Vec<MemoItemJson<Fr>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct WithdrawJson {
+    #[serde(with = "crate::utils::serde_string")]
+    pub native_amount: u64,
+    /// In the chain's usual text form, see [`crate::withdraw::ChainAddress`].
+    pub receiver: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct MemoItemJson<Fr: PrimeField> {
+    pub hash: HexNum<Fr>,
+    pub ciphertext: Bytes,
+}
+
+/// Encoding of [`Bytes`] when serializing. Both are accepted when deserializing, telling them
+/// apart by the `0x` or `base64:` prefix.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum BytesEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Bytes {
+    pub bytes: Vec<u8>,
+    pub encoding: BytesEncoding,
+}
+
+/// Field element as `0x`-prefixed 32-byte big-endian hex. Shorter hex is accepted when reading.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct HexNum<Fr: PrimeField>(pub Num<Fr>);
+
+impl<Fr: PrimeField, Fq: PrimeField> TxJson<Fr, Fq> {
+    /// Converts a transaction, decoding the delta and the memo as `C` encodes them.
+    pub fn new<C: Codec, E: Engine<Fq = Fq>>(
+        tx: &TxData<Fr, Proof<E>>,
+        encoding: BytesEncoding,
+    ) -> Self {
+        let bytes = |bytes: &[u8]| Bytes {
+            bytes: bytes.to_vec(),
+            encoding,
+        };
+
+        let asset_id = match &tx.asset_id {
+            AssetId::None => AssetIdJson::None,
+            AssetId::Id32(id) => AssetIdJson::Id32(bytes(id)),
+            AssetId::AccountId(account_id) => AssetIdJson::AccountId(account_id.clone()),
+            AssetId::Erc20(address) => AssetIdJson::Erc20(bytes(address)),
+        };
+
+        Self {
+            tx_type: tx.tx_type,
+            nullifier: HexNum(tx.nullifier),
+            out_commit: HexNum(tx.out_commit),
+            root_after: HexNum(tx.root_after),
+            delta: HexNum(tx.delta),
+            proof: ProofJson::new(&tx.proof),
+            tree_proof: ProofJson::new(&tx.tree_proof),
+            memo: bytes(&tx.memo),
+            extra_data: bytes(&tx.extra_data),
+            asset_id,
+            delta_decoded: Delta::parse(&tx.delta).map(DeltaJson::from),
+            memo_decoded: MemoJson::new::<C, _>(tx, encoding).ok(),
+        }
+    }
+
+    /// Converts back, ignoring the decoded delta and memo.
+    pub fn to_tx_data<E: Engine<Fq = Fq>>(&self) -> Result<TxData<Fr, Proof<E>>> {
+        let asset_id = match &self.asset_id {
+            AssetIdJson::None => AssetId::None,
+            AssetIdJson::Id32(id) => AssetId::Id32(id.to_array()?),
+            AssetIdJson::AccountId(account_id) => AssetId::AccountId(account_id.clone()),
+            AssetIdJson::Erc20(address) => AssetId::Erc20(address.to_array()?),
+        };
+
+        Ok(TxData {
+            tx_type: self.tx_type,
+            proof: self.proof.to_proof(),
+            tree_proof: self.tree_proof.to_proof(),
+            root_after: self.root_after.0,
+            delta: self.delta.0,
+            out_commit: self.out_commit.0,
+            nullifier: self.nullifier.0,
+            memo: self.memo.bytes.clone(),
+            extra_data: self.extra_data.bytes.clone(),
+            asset_id,
+        })
+    }
+}
+
+impl From<Delta> for DeltaJson {
+    fn from(delta: Delta) -> Self {
+        Self {
+            token_amount: delta.token_amount,
+            energy_amount: delta.energy_amount,
+            index: delta.index,
+            pool_id: delta.pool_id,
+        }
+    }
+}
+
+impl<Fr: PrimeField> MemoJson<Fr> {
+    /// Parses the memo as `C` encodes it, with the default layout.
+    pub fn new<C: Codec, P: crate::proof::Proof>(
+        tx: &TxData<Fr, P>,
+        encoding: BytesEncoding,
+    ) -> Result<Self> {
+        let bytes = |bytes: &[u8]| Bytes {
+            bytes: bytes.to_vec(),
+            encoding,
+        };
+
+        let fee = tx.fee::<C>()?;
+        let withdraw = if tx.tx_type == TxType::Withdraw {
+            Some(tx.withdraw_data::<C>()?.into())
+        } else {
+            None
+        };
+        let memo = tx.parse_memo::<C>(MemoLayout::default())?;
+
+        Ok(Self {
+            fee,
+            withdraw,
+            ephemeral_key: bytes(memo.ephemeral_key),
+            shared_secret_ciphertext: bytes(memo.shared_secret_ciphertext),
+            items: memo
+                .items()
+                .map(|item| MemoItemJson {
+                    hash: HexNum(item.hash),
+                    ciphertext: bytes(item.ciphertext),
+                })
+                .collect(),
+        })
+    }
+}
+
+impl From<WithdrawData> for WithdrawJson {
+    fn from(data: WithdrawData) -> Self {
+        Self {
+            native_amount: data.native_amount,
+            receiver: data.receiver.to_string(),
+        }
+    }
+}
+
+impl<Fq: PrimeField> ProofJson<Fq> {
+    pub fn new<E: Engine<Fq = Fq>>(proof: &Proof<E>) -> Self {
+        Self {
+            a: [HexNum(proof.a.0), HexNum(proof.a.1)],
+            b: [
+                [HexNum(proof.b.0 .0), HexNum(proof.b.0 .1)],
+                [HexNum(proof.b.1 .0), HexNum(proof.b.1 .1)],
+            ],
+            c: [HexNum(proof.c.0), HexNum(proof.c.1)],
+        }
+    }
+
+    pub fn to_proof<E: Engine<Fq = Fq>>(&self) -> Proof<E> {
+        let [[b00, b01], [b10, b11]] = self.b;
+
+        Proof {
+            a: G1Point(self.a[0].0, self.a[1].0),
+            b: G2Point((b00.0, b01.0), (b10.0, b11.0)),
+            c: G1Point(self.c[0].0, self.c[1].0),
+        }
+    }
+}
+
+impl AssetIdJson {
+    fn is_none(&self) -> bool {
+        *self == AssetIdJson::None
+    }
+}
+
+impl Bytes {
+    fn to_array<const N: usize>(&self) -> Result<[u8; N]> {
+        self.bytes.as_slice().try_into().map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("expected {N} bytes, got {}", self.bytes.len()),
+            )
+        })
+    }
+}
+
+impl Display for Bytes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.encoding {
+            BytesEncoding::Hex => write!(f, "0x{}", hex::encode(&self.bytes)),
+            BytesEncoding::Base64 => write!(f, "base64:{}", STANDARD.encode(&self.bytes)),
+        }
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        let (bytes, encoding) = if let Some(hex) = s.strip_prefix("0x") {
+            (
+                hex::decode(hex).map_err(de::Error::custom)?,
+                BytesEncoding::Hex,
+            )
+        } else if let Some(base64) = s.strip_prefix("base64:") {
+            (
+                STANDARD.decode(base64).map_err(de::Error::custom)?,
+                BytesEncoding::Base64,
+            )
+        } else {
+            return Err(de::Error::custom(
+                "byte string must be 0x-prefixed hex or base64:-prefixed base64",
+            ));
+        };
+
+        Ok(Self { bytes, encoding })
+    }
+}
+
+impl<Fr: PrimeField> Display for HexNum<Fr> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut bytes = [0u8; 32];
+        self.0.to_uint().0.put_big_endian(&mut bytes);
+        write!(f, "0x{}", hex::encode(bytes))
+    }
+}
+
+impl<Fr: PrimeField> Serialize for HexNum<Fr> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, Fr: PrimeField> Deserialize<'de> for HexNum<Fr> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let hex = s
+            .strip_prefix("0x")
+            .ok_or_else(|| de::Error::custom("field element must be 0x-prefixed hex"))?;
+
+        if hex.is_empty() || hex.len() > 64 {
+            return Err(de::Error::custom("field element must be 1 to 32 bytes"));
+        }
+
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(format!("{hex:0>64}"), &mut bytes).map_err(de::Error::custom)?;
+
+        let num = NumRepr(<Fr::Inner as Uint>::from_big_endian(&bytes));
+        Num::from_uint(num)
+            .map(HexNum)
+            .ok_or_else(|| de::Error::custom("field element exceeds the modulus"))
+    }
+}
+
+mod symbolic_tx_type {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::TxType;
+
+    pub fn serialize<S: Serializer>(tx_type: &TxType, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match tx_type {
+            TxType::Deposit => "deposit",
+            TxType::Transfer => "transfer",
+            TxType::Withdraw => "withdraw",
+        })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TxType, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "deposit" => Ok(TxType::Deposit),
+            "transfer" => Ok(TxType::Transfer),
+            "withdraw" => Ok(TxType::Withdraw),
+            tx_type => Err(D::Error::custom(format!("invalid tx type: {tx_type}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fawkes_crypto::{backend::bellman_groth16::engines::Bn256, engines::bn256::Fr};
+
+    use super::*;
+    use crate::{
+        evm::Evm,
+        utils::tests::{sample_tx, zero_proof, TestTxData},
+    };
+
+    type TestTxJson = TxJson<Fr, <Bn256 as Engine>::Fq>;
+
+    #[test]
+    fn test_json_roundtrip() {
+        let mut memo = 5u64.to_be_bytes().to_vec();
+        memo.extend_from_slice(&1u64.to_be_bytes());
+        memo.extend_from_slice(&[7; 20]);
+        let mut proof = zero_proof();
+        proof.b.1 .0 = -Num::ONE;
+        let tx = TestTxData {
+            proof,
+            root_after: Num::from(4u64),
+            delta: Delta {
+                token_amount: -10,
+                energy_amount: 0,
+                index: 128,
+                pool_id: 0,
+            }
+            .to_num(),
+            out_commit: Num::from(2u64),
+            nullifier: -Num::ONE,
+            memo,
+            extra_data: vec![1, 2],
+            asset_id: AssetId::Erc20([3; 20]),
+            ..sample_tx(TxType::Withdraw)
+        };
+
+        let json = TestTxJson::new::<Evm, _>(&tx, BytesEncoding::Hex);
+        let value = serde_json::to_value(&json).unwrap();
+        assert_eq!(value["tx_type"], "withdraw");
+        assert_eq!(value["out_commit"], format!("0x{:064x}", 2));
+        assert_eq!(value["extra_data"], "0x0102");
+        assert_eq!(value["delta_decoded"]["token_amount"], "-10");
+        assert_eq!(value["asset_id"]["erc20"], format!("0x{}", "03".repeat(20)));
+        // The memo is too short for the encrypted part.
+        assert!(value.get("memo_decoded").is_none());
+
+        let json: TestTxJson = serde_json::from_value(value).unwrap();
+        let tx2 = json.to_tx_data().unwrap();
+        assert_eq!(tx2, tx);
+        assert_eq!(tx2.asset_id, tx.asset_id);
+
+        let json = TestTxJson::new::<Evm, _>(&tx, BytesEncoding::Base64);
+        let value = serde_json::to_value(&json).unwrap();
+        assert_eq!(value["extra_data"], "base64:AQI=");
+        let json: TestTxJson = serde_json::from_value(value).unwrap();
+        assert_eq!(json.to_tx_data::<Bn256>().unwrap(), tx);
+
+        // Plain base64 of these bytes is "0xAA", which must not be read as hex.
+        for encoding in [BytesEncoding::Hex, BytesEncoding::Base64] {
+            let bytes = Bytes {
+                bytes: vec![0xd3, 0x10, 0x00],
+                encoding,
+            };
+            let value = serde_json::to_value(&bytes).unwrap();
+            assert_eq!(serde_json::from_value::<Bytes>(value).unwrap(), bytes);
+        }
+        assert!(serde_json::from_value::<Bytes>(serde_json::json!("0xAA")).is_ok());
+        assert!(serde_json::from_value::<Bytes>(serde_json::json!("AQI=")).is_err());
+
+        let modulus = serde_json::json!(format!("0x{}", "ff".repeat(32)));
+        assert!(serde_json::from_value::<HexNum<Fr>>(modulus).is_err());
+        let short = serde_json::from_value::<HexNum<Fr>>(serde_json::json!("0x2")).unwrap();
+        assert_eq!(short.0, Num::from(2u64));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_json_matches_schema() {
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../schema/tx.schema.json")).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+
+        let mut memo = 5u64.to_be_bytes().to_vec();
+        memo.extend_from_slice(&1u64.to_be_bytes());
+        memo.extend_from_slice(&[7; 20]);
+        memo.extend_from_slice(&1u32.to_le_bytes());
+        memo.extend_from_slice(&[8; 32 + 32 + 32 + 16]);
+        memo.extend(vec![9; MemoLayout::default().account_ciphertext_size]);
+        let withdraw = TestTxData {
+            delta: Delta {
+                token_amount: -10,
+                energy_amount: 0,
+                index: 128,
+                pool_id: 0,
+            }
+            .to_num(),
+            nullifier: -Num::ONE,
+            memo,
+            asset_id: AssetId::Erc20([3; 20]),
+            ..sample_tx(TxType::Withdraw)
+        };
+        let deposit = TestTxData {
+            tx_type: TxType::Deposit,
+            memo: vec![],
+            extra_data: vec![0xd3, 0x10, 0x00],
+            asset_id: AssetId::Id32([1; 32]),
+            ..withdraw.clone()
+        };
+
+        for tx in [&withdraw, &deposit] {
+            for encoding in [BytesEncoding::Hex, BytesEncoding::Base64] {
+                let value = serde_json::to_value(TestTxJson::new::<Evm, _>(tx, encoding)).unwrap();
+                if tx.tx_type == TxType::Withdraw {
+                    assert!(value["memo_decoded"]["withdraw"].is_object());
+                }
+
+                let errors = validator
+                    .iter_errors(&value)
+                    .map(|err| err.to_string())
+                    .collect::<Vec<_>>();
+                assert!(errors.is_empty(), "{value}: {errors:?}");
+                for key in value.as_object().unwrap().keys() {
+                    assert!(
+                        properties.contains_key(key),
+                        "{key} is missing in the schema"
+                    );
+                }
+            }
+        }
+
+        let mut value =
+            serde_json::to_value(TestTxJson::new::<Evm, _>(&deposit, Default::default())).unwrap();
+        value["memo"] = serde_json::json!("AQI=");
+        assert!(!validator.is_valid(&value));
+    }
+}
@@ -1,16 +1,48 @@
-use std::fmt::Debug;
+use std::{
+    fmt::{Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+    io::{Read, Write},
+};
 
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
 use fawkes_crypto::ff_uint::{Num, PrimeField};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-use crate::proof::{DebugProof, Proof};
+use crate::{
+    memo::{Memo, MemoLayout},
+    policy::{NumPolicy, NumReport},
+    proof::{DebugProof, Proof},
+    utils::write_num,
+    withdraw::{ChainAddress, WithdrawData},
+};
 
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod cosmwasm;
+pub mod delta;
 pub mod evm;
+pub mod fee;
+mod hash;
+#[cfg(feature = "groth16")]
+pub mod json;
+pub mod memo;
 pub mod near;
+pub mod policy;
+pub mod pool;
 pub mod proof;
+#[cfg(feature = "python")]
+mod python;
+pub mod relayer;
+pub mod solana;
+pub mod starknet;
 pub mod substrate;
+pub mod tron;
 mod utils;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 pub mod waves;
+pub mod withdraw;
 
 // TODO: Custom error type
 
@@ -41,6 +73,114 @@ impl TryFrom<u16> for TxType {
     }
 }
 
+/// Asset a pool operates on, in the form the chain identifies it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub enum AssetId {
+    /// The chain's native coin, or a chain format with no asset field.
+    #[default]
+    None,
+    /// 32-byte asset id, as used by Waves and Substrate.
+    Id32([u8; 32]),
+    /// Token contract account id or denom, as used by NEAR and CosmWasm.
+    AccountId(String),
+    /// ERC-20 token contract address, known to [`evm::EvmCodec::with_token`].
+    Erc20([u8; 20]),
+}
+
+impl AssetId {
+    /// Reads a 32-byte asset id, where all zeros stands for the native coin.
+    pub(crate) fn from_id32(id: [u8; 32]) -> Self {
+        if id == [0; 32] {
+            AssetId::None
+        } else {
+            AssetId::Id32(id)
+        }
+    }
+
+    /// Fails on an all zero id, which would read back as [`AssetId::None`].
+    pub(crate) fn to_id32(&self) -> std::io::Result<[u8; 32]> {
+        match self {
+            AssetId::None => Ok([0; 32]),
+            AssetId::Id32(id) if *id == [0; 32] => Err(self.ambiguous()),
+            AssetId::Id32(id) => Ok(*id),
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    /// Reads a NEAR token account id, where an empty string stands for the native coin.
+    pub(crate) fn from_account_id(account_id: String) -> Self {
+        if account_id.is_empty() {
+            AssetId::None
+        } else {
+            AssetId::AccountId(account_id)
+        }
+    }
+
+    /// Fails on an empty account id, which would read back as [`AssetId::None`].
+    pub(crate) fn to_account_id(&self) -> std::io::Result<&str> {
+        match self {
+            AssetId::None => Ok(""),
+            AssetId::AccountId(account_id) if account_id.is_empty() => Err(self.ambiguous()),
+            AssetId::AccountId(account_id) => Ok(account_id),
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    /// For formats without an asset field.
+    pub(crate) fn ensure_none(&self) -> std::io::Result<()> {
+        match self {
+            AssetId::None => Ok(()),
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    fn ambiguous(&self) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("asset id {self:?} is encoded as the native coin, use AssetId::None"),
+        )
+    }
+
+    fn unsupported(&self) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("asset id {self:?} is not supported by this chain"),
+        )
+    }
+}
+
+/// `none`, the account id, or `0x`-prefixed hex for the other ids.
+impl Display for AssetId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetId::None => f.write_str("none"),
+            AssetId::Id32(id) => write!(f, "0x{}", hex::encode(id)),
+            AssetId::AccountId(account_id) => f.write_str(account_id),
+            AssetId::Erc20(address) => write!(f, "0x{}", hex::encode(address)),
+        }
+    }
+}
+
+/// A field of [`TxData`], for [`TxData::eq_ignoring`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum TxField {
+    TxType,
+    Proof,
+    TreeProof,
+    RootAfter,
+    Delta,
+    OutCommit,
+    Nullifier,
+    Memo,
+    ExtraData,
+    AssetId,
+}
+
+impl TxField {
+    /// Fields that only some chains carry, which [`TxData::id`] leaves out.
+    pub const CHAIN_SPECIFIC: &'static [TxField] = &[TxField::AssetId];
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(bound = "")]
 pub struct TxData<Fr: PrimeField, P: Proof> {
@@ -53,7 +193,149 @@ pub struct TxData<Fr: PrimeField, P: Proof> {
     pub nullifier: Num<Fr>,
     pub memo: Vec<u8>,
     pub extra_data: Vec<u8>,
-    pub token_id: String,
+    pub asset_id: AssetId,
+}
+
+impl<Fr: PrimeField, P: Proof> TxData<Fr, P> {
+    /// Chain-independent transaction identifier.
+    ///
+    /// SHA-256 of the big-endian encoding of every field in declaration order, with `memo` and
+    /// `extra_data` prefixed by their `u32` length. `asset_id` is not included since only some
+    /// chains carry it, so re-encoding a transaction for another chain keeps its id.
+    pub fn id(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        self.write_canonical(&mut hasher)
+            .expect("writing to a hasher cannot fail");
+        hasher.finalize().into()
+    }
+
+    /// Fee the sender committed to in the first 8 bytes of the memo.
+    pub fn fee<C: Codec>(&self) -> std::io::Result<u64> {
+        (&self.memo[..]).read_u64::<C::MemoOrder>()
+    }
+
+    /// Native amount and receiver of a withdrawal, read from the memo after the fee.
+    pub fn withdraw_data<C: Codec>(&self) -> std::io::Result<WithdrawData> {
+        if self.tx_type != TxType::Withdraw {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "not a withdraw transaction",
+            ));
+        }
+
+        let mut r = self.memo.get(8..).unwrap_or_default();
+        C::read_withdraw_data(&mut r)
+    }
+
+    /// Encrypted part of the memo, following the fee and the withdraw data.
+    pub fn parse_memo<C: Codec>(&self, layout: MemoLayout) -> std::io::Result<Memo<'_, Fr>> {
+        let mut r = &self.memo[..];
+        r.read_u64::<C::MemoOrder>()?;
+
+        if self.tx_type == TxType::Withdraw {
+            C::read_withdraw_data(&mut r)?;
+        }
+
+        Memo::parse(r, layout)
+    }
+
+    /// [`Codec::hash`] of the call arguments `C` encodes, without the chain's signed transaction
+    /// envelope, so this is not the on-chain transaction id.
+    pub fn args_hash<C: Codec>(&self) -> std::io::Result<[u8; 32]> {
+        let mut buf = Vec::new();
+        C::write(self, &mut buf)?;
+        Ok(C::hash(&buf))
+    }
+
+    /// Compares every field except the `ignored` ones.
+    pub fn eq_ignoring(&self, other: &Self, ignored: &[TxField]) -> bool {
+        let eq = |field, equal: bool| equal || ignored.contains(&field);
+
+        eq(TxField::TxType, self.tx_type == other.tx_type)
+            && eq(TxField::Proof, self.proof.my_eq(&other.proof))
+            && eq(TxField::TreeProof, self.tree_proof.my_eq(&other.tree_proof))
+            && eq(TxField::RootAfter, self.root_after == other.root_after)
+            && eq(TxField::Delta, self.delta == other.delta)
+            && eq(TxField::OutCommit, self.out_commit == other.out_commit)
+            && eq(TxField::Nullifier, self.nullifier == other.nullifier)
+            && eq(TxField::Memo, self.memo == other.memo)
+            && eq(TxField::ExtraData, self.extra_data == other.extra_data)
+            && eq(TxField::AssetId, self.asset_id == other.asset_id)
+    }
+
+    /// The transaction without its [`TxField::CHAIN_SPECIFIC`] fields. Two transactions have
+    /// equal projections exactly when they have the same [`TxData::id`].
+    pub fn canonical(&self) -> Self {
+        Self {
+            asset_id: AssetId::None,
+            ..self.clone()
+        }
+    }
+
+    fn write_canonical<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        w.write_u16::<BigEndian>(self.tx_type as u16)?;
+        self.proof.write::<BigEndian, _>(w)?;
+        self.tree_proof.write::<BigEndian, _>(w)?;
+        write_num::<BigEndian, _, Fr>(w, &self.root_after)?;
+        write_num::<BigEndian, _, Fr>(w, &self.delta)?;
+        write_num::<BigEndian, _, Fr>(w, &self.out_commit)?;
+        write_num::<BigEndian, _, Fr>(w, &self.nullifier)?;
+        w.write_u32::<BigEndian>(self.memo.len() as u32)?;
+        w.write_all(&self.memo)?;
+        w.write_u32::<BigEndian>(self.extra_data.len() as u32)?;
+        w.write_all(&self.extra_data)?;
+
+        Ok(())
+    }
+}
+
+/// Wire format of a transaction on a particular chain.
+pub trait Codec {
+    /// Byte order of integers inside the memo.
+    type MemoOrder: ByteOrder;
+
+    fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> std::io::Result<TxData<Fr, P>>;
+    fn write<W: Write, Fr: PrimeField, P: Proof>(
+        data: &TxData<Fr, P>,
+        w: &mut W,
+    ) -> std::io::Result<()>;
+    /// The chain's native hash function, e.g. keccak256 for EVM.
+    fn hash(bytes: &[u8]) -> [u8; 32];
+    fn read_withdraw_address<R: Read>(r: &mut R) -> std::io::Result<ChainAddress>;
+    /// Fails with `ErrorKind::InvalidInput` if the address belongs to another chain.
+    fn write_withdraw_address<W: Write>(address: &ChainAddress, w: &mut W) -> std::io::Result<()>;
+
+    /// Like [`Codec::read`], with non-canonical field elements handled according to `policy`.
+    /// The default decodes strictly and is kept by formats that encode numbers as text.
+    fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+        r: &mut R,
+        _policy: NumPolicy,
+    ) -> std::io::Result<(TxData<Fr, P>, NumReport)> {
+        Self::read(r).map(|data| (data, NumReport::default()))
+    }
+
+    /// The depositor's signature within the extra data of a deposit. The default takes all of
+    /// it.
+    fn deposit_signature(extra_data: &[u8]) -> std::io::Result<&[u8]> {
+        Ok(extra_data)
+    }
+
+    fn read_withdraw_data<R: Read>(r: &mut R) -> std::io::Result<WithdrawData> {
+        let native_amount = r.read_u64::<Self::MemoOrder>()?;
+        let receiver = Self::read_withdraw_address(r)?;
+        receiver.validate()?;
+
+        Ok(WithdrawData {
+            native_amount,
+            receiver,
+        })
+    }
+
+    fn write_withdraw_data<W: Write>(data: &WithdrawData, w: &mut W) -> std::io::Result<()> {
+        data.receiver.validate()?;
+        w.write_u64::<Self::MemoOrder>(data.native_amount)?;
+        Self::write_withdraw_address(&data.receiver, w)
+    }
 }
 
 impl<Fr: PrimeField, P: Proof> Debug for TxData<Fr, P> {
@@ -68,7 +350,7 @@ impl<Fr: PrimeField, P: Proof> Debug for TxData<Fr, P> {
             .field("nullifier", &self.nullifier)
             .field("memo", &self.memo)
             .field("extra_data", &self.extra_data)
-            .field("token_id", &self.token_id)
+            .field("asset_id", &self.asset_id)
             .finish()
     }
 }
@@ -79,29 +361,158 @@ impl<Fr: PrimeField, P: Proof> Clone for TxData<Fr, P> {
             tx_type: self.tx_type,
             proof: self.proof.my_clone(),
             tree_proof: self.tree_proof.my_clone(),
-            root_after: self.root_after.clone(),
-            delta: self.delta.clone(),
-            out_commit: self.out_commit.clone(),
-            nullifier: self.nullifier.clone(),
+            root_after: self.root_after,
+            delta: self.delta,
+            out_commit: self.out_commit,
+            nullifier: self.nullifier,
             memo: self.memo.clone(),
             extra_data: self.extra_data.clone(),
-            token_id: self.token_id.clone(),
+            asset_id: self.asset_id.clone(),
         }
     }
 }
 
 impl<Fr: PrimeField, P: Proof> PartialEq for TxData<Fr, P> {
     fn eq(&self, other: &Self) -> bool {
-        self.tx_type == other.tx_type
-            && self.proof.my_eq(&other.proof)
-            && self.tree_proof.my_eq(&other.tree_proof)
-            && self.root_after == other.root_after
-            && self.delta == other.delta
-            && self.out_commit == other.out_commit
-            && self.nullifier == other.nullifier
-            && self.memo == other.memo
-            && self.extra_data == other.extra_data
+        self.eq_ignoring(other, &[])
     }
 }
 
 impl<Fr: PrimeField, P: Proof> Eq for TxData<Fr, P> {}
+
+impl<Fr: PrimeField, P: Proof> Hash for TxData<Fr, P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.write_canonical(&mut HashWriter(state))
+            .expect("writing to a hasher cannot fail");
+        self.asset_id.hash(state);
+    }
+}
+
+struct HashWriter<'a, H: Hasher>(&'a mut H);
+
+impl<H: Hasher> Write for HashWriter<'_, H> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        evm::Evm,
+        hash::keccak256,
+        utils::tests::{sample_tx, TestTxData},
+    };
+
+    #[test]
+    fn test_id_is_chain_independent() {
+        let data = TestTxData {
+            root_after: Num::from(4u64),
+            delta: Num::from(3u64),
+            out_commit: Num::from(2u64),
+            nullifier: Num::from(1u64),
+            memo: vec![5u8, 6u8],
+            ..sample_tx(TxType::Transfer)
+        };
+
+        let mut evm_buf = vec![];
+        evm::write(&data, &mut evm_buf).unwrap();
+        let from_evm: TestTxData = evm::read(&mut &evm_buf[..]).unwrap();
+
+        let mut near_data = data.clone();
+        near_data.asset_id = AssetId::AccountId("wrap.near".to_owned());
+        let mut near_buf = vec![];
+        near::write(&near_data, &mut near_buf).unwrap();
+        let from_near: TestTxData = near::read(&mut &near_buf[..]).unwrap();
+
+        assert_eq!(from_evm.id(), data.id());
+        assert_eq!(from_near.id(), data.id());
+        assert_ne!(data.args_hash::<Evm>().unwrap(), data.id());
+        assert_eq!(data.args_hash::<Evm>().unwrap(), keccak256(&evm_buf));
+    }
+
+    #[test]
+    fn test_withdraw_data_roundtrip() {
+        let withdraw_data = WithdrawData {
+            native_amount: 42,
+            receiver: ChainAddress::Near("alice.near".to_owned()),
+        };
+        let mut memo = 7u64.to_le_bytes().to_vec();
+        near::Near::write_withdraw_data(&withdraw_data, &mut memo).unwrap();
+        memo.extend_from_slice(&[1, 2, 3]);
+
+        let data = TestTxData {
+            memo,
+            ..sample_tx(TxType::Withdraw)
+        };
+
+        assert_eq!(data.withdraw_data::<near::Near>().unwrap(), withdraw_data);
+        assert!(Evm::write_withdraw_data(&withdraw_data, &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_asset_id_mapping() {
+        let mut data = TestTxData {
+            memo: vec![1],
+            asset_id: AssetId::Id32([3; 32]),
+            ..sample_tx(TxType::Transfer)
+        };
+
+        let mut buf = vec![];
+        waves::write(&data, &mut buf).unwrap();
+        let from_waves: TestTxData = waves::read(&mut &buf[..]).unwrap();
+        assert_eq!(from_waves.asset_id, data.asset_id);
+
+        // Substrate reads an asset id, but its write layout has no field for it.
+        assert!(substrate::write(&data, &mut vec![]).is_err());
+        assert!(evm::write(&data, &mut vec![]).is_err());
+        assert!(near::write(&data, &mut vec![]).is_err());
+
+        data.asset_id = AssetId::AccountId("usdt.tether-token.near".to_owned());
+        assert!(waves::write(&data, &mut vec![]).is_err());
+
+        // Ids that would read back as the native coin.
+        data.asset_id = AssetId::AccountId(String::new());
+        let err = near::write(&data, &mut vec![]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(cosmwasm::write(&data, &mut vec![]).is_err());
+        data.asset_id = AssetId::Id32([0; 32]);
+        assert!(waves::write(&data, &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_eq_covers_asset_id() {
+        let usdt = TestTxData {
+            root_after: Num::from(4u64),
+            delta: Num::from(3u64),
+            out_commit: Num::from(2u64),
+            nullifier: Num::from(1u64),
+            memo: vec![5],
+            extra_data: vec![6],
+            asset_id: AssetId::AccountId("usdt.tether-token.near".to_owned()),
+            ..sample_tx(TxType::Deposit)
+        };
+        let mut wnear = usdt.clone();
+        wnear.asset_id = AssetId::AccountId("wrap.near".to_owned());
+
+        assert_ne!(usdt, wnear);
+        assert!(usdt.eq_ignoring(&wnear, TxField::CHAIN_SPECIFIC));
+        assert_eq!(usdt.canonical(), wnear.canonical());
+        assert_eq!(usdt.id(), wnear.id());
+
+        wnear.memo.push(7);
+        assert!(!usdt.eq_ignoring(&wnear, TxField::CHAIN_SPECIFIC));
+        assert!(usdt.eq_ignoring(&wnear, &[TxField::Memo, TxField::AssetId]));
+
+        let set = std::collections::HashSet::from([usdt.clone(), wnear.canonical(), usdt.clone()]);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&usdt));
+        assert!(!set.contains(&wnear));
+    }
+}
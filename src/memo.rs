@@ -0,0 +1,166 @@
+use std::io::{Error, ErrorKind, Result};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use fawkes_crypto::ff_uint::{Num, PrimeField};
+
+use crate::utils::read_num;
+
+// Encrypted part of the memo, following the fee (and withdraw data):
+// # numItems                    4 bytes (little-endian)
+// # accountHash                32 bytes
+// # noteHashes                 32 bytes * (numItems - 1)
+// # ephemeralKey               32 bytes
+// # sharedSecretCiphertext     32 bytes * numItems + 16 bytes
+// # accountCiphertext           dynamic (86 bytes by default)
+// # for each note:
+// #     ephemeralKey           32 bytes
+// #     noteCiphertext          dynamic (76 bytes by default)
+
+const KEY_SIZE: usize = 32;
+const TAG_SIZE: usize = 16;
+
+/// Ciphertext sizes, which depend on the account and note formats of the circuit.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MemoLayout {
+    pub account_ciphertext_size: usize,
+    pub note_ciphertext_size: usize,
+}
+
+impl Default for MemoLayout {
+    fn default() -> Self {
+        Self {
+            account_ciphertext_size: 70 + TAG_SIZE,
+            note_ciphertext_size: 60 + TAG_SIZE,
+        }
+    }
+}
+
+/// Hashes and ciphertexts of the account and the output notes of a transaction.
+#[derive(Debug, Clone)]
+pub struct Memo<'a, Fr: PrimeField> {
+    hashes: Vec<Num<Fr>>,
+    pub ephemeral_key: &'a [u8],
+    pub shared_secret_ciphertext: &'a [u8],
+    account_ciphertext: &'a [u8],
+    notes: &'a [u8],
+    layout: MemoLayout,
+}
+
+/// An entry of the memo. Index 0 is the account, the rest are output notes.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoItem<'a, Fr: PrimeField> {
+    pub index: usize,
+    pub hash: Num<Fr>,
+    /// For notes, the note's ephemeral key followed by the encrypted note.
+    pub ciphertext: &'a [u8],
+}
+
+impl<'a, Fr: PrimeField> Memo<'a, Fr> {
+    /// Parses the encrypted part of the memo, checking that its size matches the item count.
+    pub fn parse(message: &'a [u8], layout: MemoLayout) -> Result<Self> {
+        let mut r = message;
+        let num_items = r.read_u32::<LittleEndian>()? as usize;
+
+        if num_items == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "memo has no items"));
+        }
+
+        let hashes = (0..num_items)
+            .map(|_| read_num::<LittleEndian, _, Fr>(&mut r))
+            .collect::<Result<Vec<_>>>()?;
+
+        let note_size = KEY_SIZE + layout.note_ciphertext_size;
+        let expected_len = KEY_SIZE
+            + KEY_SIZE * num_items
+            + TAG_SIZE
+            + layout.account_ciphertext_size
+            + note_size * (num_items - 1);
+
+        if r.len() != expected_len {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "memo size does not match item count",
+            ));
+        }
+
+        let ephemeral_key = take(&mut r, KEY_SIZE);
+        let shared_secret_ciphertext = take(&mut r, KEY_SIZE * num_items + TAG_SIZE);
+        let account_ciphertext = take(&mut r, layout.account_ciphertext_size);
+
+        Ok(Self {
+            hashes,
+            ephemeral_key,
+            shared_secret_ciphertext,
+            account_ciphertext,
+            notes: r,
+            layout,
+        })
+    }
+
+    pub fn num_items(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn account_hash(&self) -> Num<Fr> {
+        self.hashes[0]
+    }
+
+    pub fn note_hashes(&self) -> &[Num<Fr>] {
+        &self.hashes[1..]
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = MemoItem<'a, Fr>> + '_ {
+        let note_size = KEY_SIZE + self.layout.note_ciphertext_size;
+        let ciphertexts =
+            std::iter::once(self.account_ciphertext).chain(self.notes.chunks(note_size));
+
+        self.hashes
+            .iter()
+            .zip(ciphertexts)
+            .enumerate()
+            .map(|(index, (hash, ciphertext))| MemoItem {
+                index,
+                hash: *hash,
+                ciphertext,
+            })
+    }
+}
+
+fn take<'a>(r: &mut &'a [u8], len: usize) -> &'a [u8] {
+    let (head, tail) = r.split_at(len);
+    *r = tail;
+    head
+}
+
+#[cfg(test)]
+mod tests {
+    use fawkes_crypto::engines::bn256::Fr;
+
+    use super::*;
+
+    #[test]
+    fn test_memo_items() {
+        let layout = MemoLayout::default();
+        let mut message = 3u32.to_le_bytes().to_vec();
+        for hash in 1..=3u8 {
+            message.push(hash);
+            message.extend_from_slice(&[0; 31]);
+        }
+        message.extend_from_slice(&[0xaa; KEY_SIZE + KEY_SIZE * 3 + TAG_SIZE]);
+        message.extend_from_slice(&[0xbb; 86]);
+        message.extend_from_slice(&[0xcc; (KEY_SIZE + 76) * 2]);
+
+        let memo = Memo::<Fr>::parse(&message, layout).unwrap();
+        let items: Vec<_> = memo.items().collect();
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(memo.account_hash(), Num::from(1u64));
+        assert_eq!(items[2].index, 2);
+        assert_eq!(items[2].hash, Num::from(3u64));
+        assert_eq!(items[0].ciphertext, &[0xbb; 86][..]);
+        assert_eq!(items[1].ciphertext.len(), KEY_SIZE + 76);
+
+        message.push(0);
+        assert!(Memo::<Fr>::parse(&message, layout).is_err());
+    }
+}
@@ -1,14 +1,21 @@
-use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::io::{Read, Result, Write};
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use fawkes_crypto::ff_uint::{Num, PrimeField};
+use fawkes_crypto::ff_uint::PrimeField;
 
 use crate::{
+    hash::sha256,
+    policy::{NumPolicy, NumReader, NumReport},
     proof::Proof,
-    utils::{read_num, write_num},
-    TxData, TxType,
+    utils::{
+        read_borsh_array, read_borsh_string, write_borsh_array, write_borsh_string, write_num,
+    },
+    withdraw::{unexpected_address, ChainAddress},
+    AssetId, Codec, TxData, TxField, TxType,
 };
 
+pub mod action;
+
 // pub struct Tx {
 //     pub nullifier: U256,
 //     pub out_commit: U256,
@@ -23,13 +30,21 @@ use crate::{
 // }
 
 pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
-    let nullifier = read_num::<LittleEndian, _, Fr>(r)?;
-    let out_commit = read_num::<LittleEndian, _, Fr>(r)?;
-    let token_id = read_borsh_string(r)?;
-    let delta = read_num::<LittleEndian, _, Fr>(r)?;
-    let tx_proof = P::read::<LittleEndian, _>(r)?;
-    let root_after = read_num::<LittleEndian, _, Fr>(r)?;
-    let tree_proof = P::read::<LittleEndian, _>(r)?;
+    read_with_policy(r, NumPolicy::Strict).map(|(data, _)| data)
+}
+
+pub fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+    r: &mut R,
+    policy: NumPolicy,
+) -> Result<(TxData<Fr, P>, NumReport)> {
+    let mut nums = NumReader::new(policy);
+    let nullifier = nums.num::<LittleEndian, _, Fr>(r, TxField::Nullifier)?;
+    let out_commit = nums.num::<LittleEndian, _, Fr>(r, TxField::OutCommit)?;
+    let asset_id = AssetId::from_account_id(read_borsh_string(r)?);
+    let delta = nums.num::<LittleEndian, _, Fr>(r, TxField::Delta)?;
+    let tx_proof = nums.proof::<LittleEndian, _, P>(r, TxField::Proof)?;
+    let root_after = nums.num::<LittleEndian, _, Fr>(r, TxField::RootAfter)?;
+    let tree_proof = nums.proof::<LittleEndian, _, P>(r, TxField::TreeProof)?;
     let tx_type = r.read_u8()?;
 
     let tx_type = TxType::try_from(tx_type as u16)?;
@@ -38,7 +53,7 @@ pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P
     let mut extra_data = vec![];
     r.read_to_end(&mut extra_data)?;
 
-    Ok(TxData {
+    let data = TxData {
         tx_type,
         proof: tx_proof,
         tree_proof,
@@ -48,14 +63,16 @@ pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P
         nullifier,
         memo,
         extra_data,
-        token_id,
-    })
+        asset_id,
+    };
+
+    Ok((data, nums.finish()?))
 }
 
 pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
     write_num::<LittleEndian, _, _>(w, &data.nullifier)?;
     write_num::<LittleEndian, _, Fr>(w, &data.out_commit)?;
-    write_borsh_string(w, &data.token_id)?;
+    write_borsh_string(w, data.asset_id.to_account_id()?)?;
     write_num::<LittleEndian, _, Fr>(w, &data.delta)?;
     data.proof.write::<LittleEndian, _>(w)?;
     write_num::<LittleEndian, _, Fr>(w, &data.root_after)?;
@@ -67,28 +84,76 @@ pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W
     Ok(())
 }
 
-fn read_borsh_string<R: Read>(r: &mut R) -> Result<String> {
-    let len = r.read_u32::<LittleEndian>()?;
-    let mut buf = vec![0u8; len as usize];
-    r.read_exact(&mut buf)?;
-    Ok(String::from_utf8(buf).map_err(|_| Error::new(ErrorKind::InvalidData, "invalid utf8"))?)
-}
+/// Borsh-encoded NEAR `transact` arguments. Hashed with sha256.
+pub struct Near;
 
-fn write_borsh_string<W: Write>(w: &mut W, s: &str) -> Result<()> {
-    w.write_u32::<LittleEndian>(s.len() as u32)?;
-    w.write_all(s.as_bytes())?;
-    Ok(())
-}
+impl Codec for Near {
+    type MemoOrder = LittleEndian;
+
+    fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
+        read(r)
+    }
+
+    fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+        r: &mut R,
+        policy: NumPolicy,
+    ) -> Result<(TxData<Fr, P>, NumReport)> {
+        read_with_policy(r, policy)
+    }
 
-fn read_borsh_array<R: Read>(r: &mut R) -> Result<Vec<u8>> {
-    let len = r.read_u32::<LittleEndian>()?;
-    let mut buf = vec![0u8; len as usize];
-    r.read_exact(&mut buf)?;
-    Ok(buf)
+    fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+        write(data, w)
+    }
+
+    fn read_withdraw_address<R: Read>(r: &mut R) -> Result<ChainAddress> {
+        Ok(ChainAddress::Near(read_borsh_string(r)?))
+    }
+
+    fn write_withdraw_address<W: Write>(address: &ChainAddress, w: &mut W) -> Result<()> {
+        match address {
+            ChainAddress::Near(account_id) => write_borsh_string(w, account_id),
+            _ => Err(unexpected_address(address)),
+        }
+    }
+
+    fn hash(bytes: &[u8]) -> [u8; 32] {
+        sha256(bytes)
+    }
 }
 
-fn write_borsh_array<W: Write>(w: &mut W, s: &[u8]) -> Result<()> {
-    w.write_u32::<LittleEndian>(s.len() as u32)?;
-    w.write_all(s)?;
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use proptest::{collection::vec, prelude::*, sample::Index};
+
+    use super::*;
+    use crate::utils::tests::{arb_tx_data, check_roundtrip, check_truncated, TestFr, TestProof};
+
+    fn asset_id() -> BoxedStrategy<AssetId> {
+        prop_oneof![
+            Just(AssetId::None),
+            vec(any::<char>(), 1..32)
+                .prop_map(|account_id| AssetId::AccountId(account_id.into_iter().collect())),
+        ]
+        .boxed()
+    }
+
+    fn extra_data(_: TxType) -> BoxedStrategy<Vec<u8>> {
+        vec(any::<u8>(), 0..128).boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn test_near_roundtrip(
+            tx in arb_tx_data::<TestProof>(asset_id, extra_data),
+            len in any::<Index>(),
+        ) {
+            check_roundtrip::<Near, _>(&tx)?;
+            check_truncated::<Near, _>(&tx, len)?;
+        }
+
+        #[test]
+        fn test_near_read_random_bytes(bytes in vec(any::<u8>(), 0..1024)) {
+            let _ = read::<_, TestFr, TestProof>(&mut &bytes[..]);
+        }
+    }
 }
@@ -0,0 +1,143 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use fawkes_crypto::ff_uint::PrimeField;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    proof::Proof,
+    utils::{read_borsh_array, read_borsh_string, write_borsh_array, write_borsh_string},
+    TxData,
+};
+
+/// Method the pool contract exposes for transactions, unless configured otherwise.
+pub const DEFAULT_METHOD_NAME: &str = "transact";
+
+/// Borsh tag of `Action::FunctionCall` in NEAR transactions.
+const FUNCTION_CALL_TAG: u8 = 2;
+
+/// A NEAR `FunctionCall` action. Serializes to the JSON representation used by the RPC, with
+/// base64 args and the deposit as a string.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub method_name: String,
+    #[serde(with = "crate::utils::serde_base64")]
+    pub args: Vec<u8>,
+    pub gas: u64,
+    #[serde(with = "crate::utils::serde_string")]
+    pub deposit: u128,
+}
+
+#[derive(Serialize, Deserialize)]
+enum RpcAction {
+    FunctionCall(FunctionCall),
+}
+
+impl FunctionCall {
+    /// Wraps a transaction into a call of `method_name` on the pool contract.
+    pub fn new<Fr: PrimeField, P: Proof>(
+        data: &TxData<Fr, P>,
+        method_name: &str,
+        gas: u64,
+        deposit: u128,
+    ) -> Result<Self> {
+        let mut args = Vec::new();
+        super::write(data, &mut args)?;
+
+        Ok(Self {
+            method_name: method_name.to_owned(),
+            args,
+            gas,
+            deposit,
+        })
+    }
+
+    /// Unwraps the transaction, failing if the call is not to `method_name`.
+    pub fn tx_data<Fr: PrimeField, P: Proof>(&self, method_name: &str) -> Result<TxData<Fr, P>> {
+        if self.method_name != method_name {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unexpected method {}", self.method_name),
+            ));
+        }
+
+        super::read(&mut &self.args[..])
+    }
+
+    /// Reads a borsh-encoded `Action`, as found in signed transactions and receipts.
+    pub fn read_action<R: Read>(r: &mut R) -> Result<Self> {
+        if r.read_u8()? != FUNCTION_CALL_TAG {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "not a function call action",
+            ));
+        }
+
+        Ok(Self {
+            method_name: read_borsh_string(r)?,
+            args: read_borsh_array(r)?,
+            gas: r.read_u64::<LittleEndian>()?,
+            deposit: r.read_u128::<LittleEndian>()?,
+        })
+    }
+
+    pub fn write_action<W: Write>(&self, w: &mut W) -> Result<()> {
+        w.write_u8(FUNCTION_CALL_TAG)?;
+        write_borsh_string(w, &self.method_name)?;
+        write_borsh_array(w, &self.args)?;
+        w.write_u64::<LittleEndian>(self.gas)?;
+        w.write_u128::<LittleEndian>(self.deposit)?;
+
+        Ok(())
+    }
+
+    /// Action as it appears in RPC responses: `{"FunctionCall": {...}}`.
+    pub fn to_rpc_json(&self) -> serde_json::Value {
+        serde_json::to_value(RpcAction::FunctionCall(self.clone()))
+            .expect("function call is always serializable")
+    }
+
+    pub fn from_rpc_json(value: serde_json::Value) -> Result<Self> {
+        let RpcAction::FunctionCall(call) =
+            serde_json::from_value(value).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        Ok(call)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fawkes_crypto::ff_uint::Num;
+
+    use super::*;
+    use crate::{
+        utils::tests::{sample_tx, TestFr, TestProof, TestTxData},
+        AssetId, TxType,
+    };
+
+    #[test]
+    fn test_function_call_roundtrip() {
+        let data = TestTxData {
+            nullifier: Num::from(1u64),
+            memo: vec![1, 2, 3],
+            asset_id: AssetId::AccountId("wrap.near".to_owned()),
+            ..sample_tx(TxType::Transfer)
+        };
+
+        let call = FunctionCall::new(&data, DEFAULT_METHOD_NAME, 300_000_000_000_000, 1).unwrap();
+
+        let mut action = vec![];
+        call.write_action(&mut action).unwrap();
+        assert_eq!(action[0], FUNCTION_CALL_TAG);
+        let call2 = FunctionCall::read_action(&mut &action[..]).unwrap();
+        assert_eq!(call2, call);
+
+        let json = call.to_rpc_json();
+        assert_eq!(json["FunctionCall"]["method_name"], "transact");
+        assert_eq!(json["FunctionCall"]["deposit"], "1");
+        let call3 = FunctionCall::from_rpc_json(json).unwrap();
+        assert_eq!(call3, call);
+
+        assert_eq!(call3.tx_data(DEFAULT_METHOD_NAME).ok(), Some(data));
+        assert!(call3.tx_data::<TestFr, TestProof>("deposit").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! Decoding of field elements encoded as integers at or above the field's modulus.
+//!
+//! Current pool contracts reject such encodings, but some deployed ones reduced them instead, so
+//! historical transactions with non-canonical elements exist on chain.
+
+use std::io::{Error, ErrorKind, Read, Result};
+
+use fawkes_crypto::ff_uint::{Num, PrimeField};
+
+use crate::{
+    proof::Proof,
+    utils::{read_num_with_policy, ByteOrderExt},
+    TxField,
+};
+
+/// What to do with a non-canonical field element.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum NumPolicy {
+    /// Fail on the first non-canonical element.
+    #[default]
+    Strict,
+    /// Reduce non-canonical elements modulo the field's modulus.
+    Reduce,
+    /// Decode the whole transaction, then fail listing every field with a non-canonical element.
+    Reject,
+}
+
+/// Fields of a decoded transaction that held non-canonical elements, in decoding order.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct NumReport {
+    non_canonical: Vec<TxField>,
+}
+
+impl NumReport {
+    pub fn is_canonical(&self) -> bool {
+        self.non_canonical.is_empty()
+    }
+
+    pub fn non_canonical(&self) -> &[TxField] {
+        &self.non_canonical
+    }
+}
+
+/// Reads the field elements of a transaction, keeping track of non-canonical ones.
+pub(crate) struct NumReader {
+    policy: NumPolicy,
+    report: NumReport,
+}
+
+impl NumReader {
+    pub(crate) fn new(policy: NumPolicy) -> Self {
+        Self {
+            policy,
+            report: NumReport::default(),
+        }
+    }
+
+    pub(crate) fn num<O: ByteOrderExt, R: Read, Fr: PrimeField>(
+        &mut self,
+        r: &mut R,
+        field: TxField,
+    ) -> Result<Num<Fr>> {
+        let (num, canonical) = read_num_with_policy::<O, _, Fr>(r, self.policy)?;
+        self.record(field, canonical);
+        Ok(num)
+    }
+
+    pub(crate) fn proof<O: ByteOrderExt, R: Read, P: Proof>(
+        &mut self,
+        r: &mut R,
+        field: TxField,
+    ) -> Result<P> {
+        let (proof, canonical) = P::read_with_policy::<O, _>(r, self.policy)?;
+        self.record(field, canonical);
+        Ok(proof)
+    }
+
+    pub(crate) fn finish(self) -> Result<NumReport> {
+        if self.policy == NumPolicy::Reject && !self.report.is_canonical() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "non-canonical field elements in {:?}",
+                    self.report.non_canonical
+                ),
+            ));
+        }
+
+        Ok(self.report)
+    }
+
+    fn record(&mut self, field: TxField, canonical: bool) {
+        if !canonical {
+            self.report.non_canonical.push(field);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fawkes_crypto::ff_uint::{NumRepr, Uint};
+
+    use super::*;
+    use crate::{
+        evm::{Evm, EvmCodec},
+        utils::tests::{sample_tx, TestFr, TestProof, TestTxData},
+        waves::Waves,
+        Codec, TxType,
+    };
+
+    fn tx() -> TestTxData {
+        TestTxData {
+            root_after: Num::from(4u64),
+            delta: Num::from(3u64),
+            out_commit: Num::from(2u64),
+            nullifier: Num::from(1u64),
+            memo: vec![5; 10],
+            ..sample_tx(TxType::Transfer)
+        }
+    }
+
+    fn read<C: Codec>(bytes: &[u8], policy: NumPolicy) -> Result<(TestTxData, NumReport)> {
+        C::read_with_policy(&mut &bytes[..], policy)
+    }
+
+    #[test]
+    fn test_num_policy() {
+        // The nullifier plus the modulus, and the first coordinate of the proof.
+        let mut bytes = vec![];
+        Evm::write(&tx(), &mut bytes).unwrap();
+        (Num::<TestFr>::MODULUS + NumRepr::from(1u64))
+            .0
+            .put_big_endian(&mut bytes[4..4 + 32]);
+        bytes[4 + 32 * 3..4 + 32 * 4].fill(0xff);
+
+        assert!(read::<Evm>(&bytes, NumPolicy::Strict).is_err());
+        let err = read::<Evm>(&bytes, NumPolicy::Reject).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("[Nullifier, Proof]"));
+
+        let (data, report) = read::<Evm>(&bytes, NumPolicy::Reduce).unwrap();
+        assert_eq!(report.non_canonical(), [TxField::Nullifier, TxField::Proof]);
+        assert_eq!(data.nullifier, Num::from(1u64));
+        assert_eq!(data.out_commit, Num::from(2u64));
+
+        let codec = EvmCodec::default();
+        let (_, _, report) = codec
+            .read_with_policy::<_, TestFr, TestProof>(&mut &bytes[..], NumPolicy::Reduce)
+            .unwrap();
+        assert_eq!(report.non_canonical(), [TxField::Nullifier, TxField::Proof]);
+        assert!(codec
+            .read_with_policy::<_, TestFr, TestProof>(&mut &bytes[..], NumPolicy::Strict)
+            .is_err());
+
+        let mut canonical = vec![];
+        Waves::write(&tx(), &mut canonical).unwrap();
+        for policy in [NumPolicy::Strict, NumPolicy::Reduce, NumPolicy::Reject] {
+            let (data, report) = read::<Waves>(&canonical, policy).unwrap();
+            assert!(report.is_canonical());
+            assert_eq!(data, tx());
+        }
+    }
+}
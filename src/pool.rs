@@ -0,0 +1,208 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    time::{Duration, Instant},
+};
+
+use fawkes_crypto::ff_uint::{Num, NumRepr, PrimeField};
+
+use crate::{delta::Delta, proof::Proof, Codec, TxData};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PoolError {
+    /// A transaction with the same nullifier is already pending and pays at least as much.
+    Conflict { pending_fee: u64, fee: u64 },
+    /// The delta of the transaction could not be unpacked.
+    InvalidDelta,
+    /// The memo is too short to hold the fee.
+    InvalidFee,
+}
+
+impl Display for PoolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PoolError::Conflict { pending_fee, fee } => write!(
+                f,
+                "nullifier already pending with fee {pending_fee}, got fee {fee}"
+            ),
+            PoolError::InvalidDelta => write!(f, "invalid delta"),
+            PoolError::InvalidFee => write!(f, "invalid memo fee"),
+        }
+    }
+}
+
+impl std::error::Error for PoolError {}
+
+pub struct PooledTx<Fr: PrimeField, P: Proof> {
+    pub tx: TxData<Fr, P>,
+    pub fee: u64,
+    /// Tree index the transaction expects its `out_commit` to be inserted at.
+    pub index: u64,
+    pub received_at: Instant,
+}
+
+/// Pending transactions keyed by nullifier.
+///
+/// Two transactions spending the same nullifier can't both be mined, so only the one paying the
+/// higher memo fee is kept.
+pub struct TxPool<Fr: PrimeField, P: Proof> {
+    txs: HashMap<NumRepr<Fr::Inner>, PooledTx<Fr, P>>,
+    max_age: Duration,
+}
+
+impl<Fr: PrimeField, P: Proof> TxPool<Fr, P> {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            txs: HashMap::new(),
+            max_age,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.txs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+
+    pub fn get(&self, nullifier: &Num<Fr>) -> Option<&PooledTx<Fr, P>> {
+        self.txs.get(&nullifier.to_uint())
+    }
+
+    /// Adds a transaction, ranked by the fee it committed to in the memo as encoded by `C`.
+    ///
+    /// If a transaction with the same nullifier is pending, it is replaced only when the fee is
+    /// strictly higher, and the replaced transaction is returned.
+    pub fn insert<C: Codec>(
+        &mut self,
+        tx: TxData<Fr, P>,
+        now: Instant,
+    ) -> Result<Option<TxData<Fr, P>>, PoolError> {
+        let fee = tx.fee::<C>().map_err(|_| PoolError::InvalidFee)?;
+        let index = Delta::parse(&tx.delta)
+            .ok_or(PoolError::InvalidDelta)?
+            .index;
+        let key = tx.nullifier.to_uint();
+
+        if let Some(pending) = self.txs.get(&key) {
+            if fee <= pending.fee {
+                return Err(PoolError::Conflict {
+                    pending_fee: pending.fee,
+                    fee,
+                });
+            }
+        }
+
+        let pooled = PooledTx {
+            tx,
+            fee,
+            index,
+            received_at: now,
+        };
+
+        Ok(self.txs.insert(key, pooled).map(|replaced| replaced.tx))
+    }
+
+    pub fn remove(&mut self, nullifier: &Num<Fr>) -> Option<TxData<Fr, P>> {
+        self.txs
+            .remove(&nullifier.to_uint())
+            .map(|pooled| pooled.tx)
+    }
+
+    /// Pending transactions in the order they expect to be included: by tree index, then by
+    /// arrival time.
+    pub fn ordered(&self) -> Vec<&PooledTx<Fr, P>> {
+        let mut txs: Vec<_> = self.txs.values().collect();
+        txs.sort_by_key(|pooled| (pooled.index, pooled.received_at));
+        txs
+    }
+
+    /// Pending transactions that expect to be inserted at `index`.
+    pub fn at_index(&self, index: u64) -> impl Iterator<Item = &PooledTx<Fr, P>> {
+        self.txs
+            .values()
+            .filter(move |pooled| pooled.index == index)
+    }
+
+    /// Removes transactions that have been pending for longer than the pool's max age.
+    pub fn evict_expired(&mut self, now: Instant) -> Vec<TxData<Fr, P>> {
+        let max_age = self.max_age;
+        let expired: Vec<_> = self
+            .txs
+            .iter()
+            .filter(|(_, pooled)| now.saturating_duration_since(pooled.received_at) > max_age)
+            .map(|(key, _)| *key)
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|key| self.txs.remove(&key))
+            .map(|pooled| pooled.tx)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        evm::Evm,
+        utils::tests::{sample_tx, TestTxData},
+        TxType,
+    };
+
+    fn tx(nullifier: u64, index: u64, fee: u64) -> TestTxData {
+        let delta = Delta {
+            token_amount: -5,
+            energy_amount: 0,
+            index,
+            pool_id: 0,
+        };
+
+        TestTxData {
+            delta: delta.to_num(),
+            nullifier: Num::from(nullifier),
+            memo: fee.to_be_bytes().to_vec(),
+            ..sample_tx(TxType::Transfer)
+        }
+    }
+
+    #[test]
+    fn test_pool_replacement_and_eviction() {
+        let start = Instant::now();
+        let mut pool = TxPool::new(Duration::from_secs(10));
+
+        assert_eq!(pool.insert::<Evm>(tx(1, 256, 10), start), Ok(None));
+        assert_eq!(pool.insert::<Evm>(tx(2, 128, 10), start), Ok(None));
+        assert_eq!(
+            pool.insert::<Evm>(tx(1, 384, 10), start),
+            Err(PoolError::Conflict {
+                pending_fee: 10,
+                fee: 10
+            })
+        );
+        assert_eq!(
+            pool.insert::<Evm>(tx(1, 384, 11), start),
+            Ok(Some(tx(1, 256, 10)))
+        );
+
+        let mut no_fee = tx(4, 128, 0);
+        no_fee.memo.truncate(7);
+        assert_eq!(
+            pool.insert::<Evm>(no_fee, start),
+            Err(PoolError::InvalidFee)
+        );
+
+        let indices: Vec<_> = pool.ordered().iter().map(|pooled| pooled.index).collect();
+        assert_eq!(indices, [128, 384]);
+
+        assert_eq!(
+            pool.insert::<Evm>(tx(3, 512, 1), start + Duration::from_secs(5)),
+            Ok(None)
+        );
+        let evicted = pool.evict_expired(start + Duration::from_secs(11));
+        assert_eq!(evicted.len(), 2);
+        assert_eq!(pool.len(), 1);
+    }
+}
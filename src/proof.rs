@@ -3,6 +3,7 @@ use std::{
     io::{Read, Write},
 };
 
+#[cfg(feature = "plonk")]
 use byteorder::{ReadBytesExt, WriteBytesExt};
 #[cfg(feature = "groth16")]
 use fawkes_crypto::backend::bellman_groth16::{
@@ -13,9 +14,11 @@ use fawkes_crypto::backend::bellman_groth16::{
 use fawkes_crypto::backend::plonk::prover::Proof as PlonkProof;
 use serde::{Deserialize, Serialize};
 
-use crate::utils::ByteOrderExt;
+#[cfg(feature = "plonk")]
+use crate::utils::read_bytes;
 #[cfg(feature = "groth16")]
-use crate::utils::{read_num, write_num};
+use crate::utils::{read_num_with_policy, write_num};
+use crate::{policy::NumPolicy, utils::ByteOrderExt};
 
 // TODO: Find a more elegant way to do this or just implement this in fawkes-crypto.
 pub trait Proof: Serialize + for<'a> Deserialize<'a> {
@@ -24,6 +27,15 @@ pub trait Proof: Serialize + for<'a> Deserialize<'a> {
     fn my_eq(&self, other: &Self) -> bool;
     fn write<O: ByteOrderExt, W: Write>(&self, w: &mut W) -> std::io::Result<()>;
     fn read<O: ByteOrderExt, R: Read>(r: &mut R) -> std::io::Result<Self>;
+
+    /// Like [`Proof::read`], also returning whether every field element was canonical. Proofs
+    /// without field elements in their encoding can keep the default.
+    fn read_with_policy<O: ByteOrderExt, R: Read>(
+        r: &mut R,
+        _policy: NumPolicy,
+    ) -> std::io::Result<(Self, bool)> {
+        Self::read::<O, _>(r).map(|proof| (proof, true))
+    }
 }
 
 pub struct DebugProof<'a, P: Proof>(pub &'a P);
@@ -84,30 +96,29 @@ impl<E: Groth16Engine> Proof for Groth16Proof<E> {
     }
 
     fn read<O: ByteOrderExt, R: Read>(r: &mut R) -> std::io::Result<Self> {
+        Self::read_with_policy::<O, _>(r, NumPolicy::Strict).map(|(proof, _)| proof)
+    }
+
+    fn read_with_policy<O: ByteOrderExt, R: Read>(
+        r: &mut R,
+        policy: NumPolicy,
+    ) -> std::io::Result<(Self, bool)> {
         let mut bytes = [0u8; 32 * 8];
         r.read_exact(&mut bytes)?;
 
         let mut r = &bytes[..];
-        let a = Groth16G1Point(
-            read_num::<O, _, E::Fq>(&mut r)?,
-            read_num::<O, _, E::Fq>(&mut r)?,
-        );
-        let b = Groth16G2Point(
-            (
-                read_num::<O, _, E::Fq>(&mut r)?,
-                read_num::<O, _, E::Fq>(&mut r)?,
-            ),
-            (
-                read_num::<O, _, E::Fq>(&mut r)?,
-                read_num::<O, _, E::Fq>(&mut r)?,
-            ),
-        );
-        let c = Groth16G1Point(
-            read_num::<O, _, E::Fq>(&mut r)?,
-            read_num::<O, _, E::Fq>(&mut r)?,
-        );
-
-        Ok(Self { a, b, c })
+        let mut canonical = true;
+        let mut read = || {
+            let (num, is_canonical) = read_num_with_policy::<O, _, E::Fq>(&mut r, policy)?;
+            canonical &= is_canonical;
+            std::io::Result::Ok(num)
+        };
+
+        let a = Groth16G1Point(read()?, read()?);
+        let b = Groth16G2Point((read()?, read()?), (read()?, read()?));
+        let c = Groth16G1Point(read()?, read()?);
+
+        Ok((Self { a, b, c }, canonical))
     }
 }
 
@@ -134,9 +145,7 @@ impl Proof for PlonkProof {
 
     fn read<O: ByteOrderExt, R: Read>(r: &mut R) -> std::io::Result<Self> {
         let len = r.read_u32::<O>()?;
-        let mut buf = vec![0u8; len as usize];
-        r.read_exact(&mut buf)?;
 
-        Ok(Self(buf))
+        Ok(Self(read_bytes(r, len as usize)?))
     }
 }
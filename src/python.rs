@@ -0,0 +1,366 @@
+//! PyO3 bindings, built as the `zeropool_tx` Python module with `maturin build`.
+//!
+//! Transactions are dicts with the fields of [`TxData`]. Field elements are ints, proofs are
+//! dicts of `a`, `b` and `c` with the coordinates as int tuples, `memo` and `extra_data` are
+//! bytes and `tx_type` is `"deposit"`, `"transfer"` or `"withdraw"`. `asset_id` is `None` for the
+//! native coin, 32 bytes for an asset id, 20 bytes for an ERC-20 address or a str account id.
+
+use std::io::Error;
+
+use fawkes_crypto::{
+    backend::bellman_groth16::{
+        engines::{Bn256, Engine},
+        group::{G1Point, G2Point},
+        prover::Proof,
+    },
+    engines::bn256::Fr,
+    ff_uint::{Num, PrimeField},
+};
+use pyo3::{
+    exceptions::{PyKeyError, PyTypeError, PyValueError},
+    prelude::*,
+    types::{PyBytes, PyDict, PyInt, PyString},
+};
+
+use crate::{
+    cosmwasm::CosmWasm, delta::Delta, evm::Evm, memo::MemoLayout, near::Near, solana::Solana,
+    substrate::Substrate, tron::Tron, waves::Waves, withdraw::ChainAddress, AssetId, Codec, TxType,
+};
+
+type Fq = <Bn256 as Engine>::Fq;
+type TxData = crate::TxData<Fr, Proof<Bn256>>;
+
+/// Runs `$body` with `$codec` bound to the codec type of the chain named `$chain`.
+macro_rules! with_codec {
+    ($chain:expr, $codec:ident => $body:expr) => {
+        match $chain {
+            "evm" => {
+                type $codec = Evm;
+                $body
+            }
+            "near" => {
+                type $codec = Near;
+                $body
+            }
+            "substrate" => {
+                type $codec = Substrate;
+                $body
+            }
+            "waves" => {
+                type $codec = Waves;
+                $body
+            }
+            "solana" => {
+                type $codec = Solana;
+                $body
+            }
+            "tron" => {
+                type $codec = Tron;
+                $body
+            }
+            "cosmwasm" => {
+                type $codec = CosmWasm;
+                $body
+            }
+            chain => Err(PyValueError::new_err(format!("unknown chain: {chain}"))),
+        }
+    };
+}
+
+#[pymodule]
+fn zeropool_tx(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(decode, m)?)?;
+    m.add_function(wrap_pyfunction!(encode, m)?)?;
+    m.add_function(wrap_pyfunction!(tx_id, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_delta, m)?)?;
+    m.add_function(wrap_pyfunction!(tx_fee, m)?)?;
+    m.add_function(wrap_pyfunction!(withdraw_data, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_memo, m)?)?;
+    Ok(())
+}
+
+/// Decodes a transaction of `chain` into a dict.
+#[pyfunction]
+fn decode<'py>(py: Python<'py>, chain: &str, data: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+    let tx: TxData = with_codec!(chain, C => C::read(&mut &data[..]).map_err(to_py_error))?;
+    tx_to_py(py, &tx)
+}
+
+/// Encodes a transaction dict for `chain`.
+#[pyfunction]
+fn encode<'py>(
+    py: Python<'py>,
+    chain: &str,
+    tx: &Bound<'py, PyDict>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let tx = tx_from_py(tx)?;
+    let mut bytes = Vec::new();
+    with_codec!(chain, C => C::write(&tx, &mut bytes).map_err(to_py_error))?;
+    Ok(PyBytes::new(py, &bytes))
+}
+
+/// Chain-independent transaction id.
+#[pyfunction]
+fn tx_id<'py>(py: Python<'py>, tx: &Bound<'py, PyDict>) -> PyResult<Bound<'py, PyBytes>> {
+    Ok(PyBytes::new(py, &tx_from_py(tx)?.id()))
+}
+
+/// Unpacks `delta` into `token_amount`, `energy_amount`, `index` and `pool_id`.
+#[pyfunction]
+fn parse_delta<'py>(py: Python<'py>, delta: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyDict>> {
+    let delta = Delta::parse(&num_from_py::<Fr>(delta)?)
+        .ok_or_else(|| PyValueError::new_err("invalid delta"))?;
+
+    let dict = PyDict::new(py);
+    dict.set_item("token_amount", delta.token_amount)?;
+    dict.set_item("energy_amount", delta.energy_amount)?;
+    dict.set_item("index", delta.index)?;
+    dict.set_item("pool_id", delta.pool_id)?;
+    Ok(dict)
+}
+
+/// Fee from the first 8 bytes of the memo.
+#[pyfunction]
+fn tx_fee(chain: &str, tx: &Bound<'_, PyDict>) -> PyResult<u64> {
+    let tx = tx_from_py(tx)?;
+    with_codec!(chain, C => tx.fee::<C>().map_err(to_py_error))
+}
+
+/// Native amount and receiver of a withdrawal. The receiver is bytes, or a str for NEAR and
+/// Cosmos accounts.
+#[pyfunction]
+fn withdraw_data<'py>(
+    py: Python<'py>,
+    chain: &str,
+    tx: &Bound<'py, PyDict>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let tx = tx_from_py(tx)?;
+    let data = with_codec!(chain, C => tx.withdraw_data::<C>().map_err(to_py_error))?;
+
+    let receiver = match &data.receiver {
+        ChainAddress::Evm(address) => PyBytes::new(py, address).into_any(),
+        ChainAddress::Waves(address) => PyBytes::new(py, address).into_any(),
+        ChainAddress::Substrate(address) | ChainAddress::Solana(address) => {
+            PyBytes::new(py, address).into_any()
+        }
+        ChainAddress::Tron(address) => PyBytes::new(py, address).into_any(),
+        ChainAddress::Near(account_id) | ChainAddress::Cosmos(account_id) => {
+            PyString::new(py, account_id).into_any()
+        }
+    };
+
+    let dict = PyDict::new(py);
+    dict.set_item("native_amount", data.native_amount)?;
+    dict.set_item("receiver", receiver)?;
+    Ok(dict)
+}
+
+/// Parses the encrypted part of the memo with the default layout. Item 0 is the account.
+#[pyfunction]
+fn parse_memo<'py>(
+    py: Python<'py>,
+    chain: &str,
+    tx: &Bound<'py, PyDict>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let tx = tx_from_py(tx)?;
+    let memo =
+        with_codec!(chain, C => tx.parse_memo::<C>(MemoLayout::default()).map_err(to_py_error))?;
+
+    let items = memo
+        .items()
+        .map(|item| {
+            let dict = PyDict::new(py);
+            dict.set_item("index", item.index)?;
+            dict.set_item("hash", num_to_py(py, &item.hash)?)?;
+            dict.set_item("ciphertext", PyBytes::new(py, item.ciphertext))?;
+            Ok(dict)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let dict = PyDict::new(py);
+    dict.set_item("ephemeral_key", PyBytes::new(py, memo.ephemeral_key))?;
+    dict.set_item(
+        "shared_secret_ciphertext",
+        PyBytes::new(py, memo.shared_secret_ciphertext),
+    )?;
+    dict.set_item("items", items)?;
+    Ok(dict)
+}
+
+fn tx_to_py<'py>(py: Python<'py>, tx: &TxData) -> PyResult<Bound<'py, PyDict>> {
+    let tx_type = match tx.tx_type {
+        TxType::Deposit => "deposit",
+        TxType::Transfer => "transfer",
+        TxType::Withdraw => "withdraw",
+    };
+    let asset_id = match &tx.asset_id {
+        AssetId::None => py.None().into_bound(py),
+        AssetId::Id32(id) => PyBytes::new(py, id).into_any(),
+        AssetId::AccountId(account_id) => PyString::new(py, account_id).into_any(),
+        AssetId::Erc20(address) => PyBytes::new(py, address).into_any(),
+    };
+
+    let dict = PyDict::new(py);
+    dict.set_item("tx_type", tx_type)?;
+    dict.set_item("proof", proof_to_py(py, &tx.proof)?)?;
+    dict.set_item("tree_proof", proof_to_py(py, &tx.tree_proof)?)?;
+    dict.set_item("root_after", num_to_py(py, &tx.root_after)?)?;
+    dict.set_item("delta", num_to_py(py, &tx.delta)?)?;
+    dict.set_item("out_commit", num_to_py(py, &tx.out_commit)?)?;
+    dict.set_item("nullifier", num_to_py(py, &tx.nullifier)?)?;
+    dict.set_item("memo", PyBytes::new(py, &tx.memo))?;
+    dict.set_item("extra_data", PyBytes::new(py, &tx.extra_data))?;
+    dict.set_item("asset_id", asset_id)?;
+    Ok(dict)
+}
+
+fn tx_from_py(dict: &Bound<'_, PyDict>) -> PyResult<TxData> {
+    let tx_type = match item(dict, "tx_type")?.extract::<String>()?.as_str() {
+        "deposit" => TxType::Deposit,
+        "transfer" => TxType::Transfer,
+        "withdraw" => TxType::Withdraw,
+        tx_type => return Err(PyValueError::new_err(format!("invalid tx type: {tx_type}"))),
+    };
+
+    let asset_id = item(dict, "asset_id")?;
+    let asset_id = if asset_id.is_none() {
+        AssetId::None
+    } else if let Ok(account_id) = asset_id.cast::<PyString>() {
+        AssetId::AccountId(account_id.to_str()?.to_owned())
+    } else {
+        let bytes = bytes_from_py(&asset_id)?;
+        match bytes.len() {
+            32 => AssetId::Id32(bytes.try_into().unwrap()),
+            20 => AssetId::Erc20(bytes.try_into().unwrap()),
+            _ => return Err(PyValueError::new_err("asset id must be 32 or 20 bytes")),
+        }
+    };
+
+    Ok(TxData {
+        tx_type,
+        proof: proof_from_py(&item(dict, "proof")?)?,
+        tree_proof: proof_from_py(&item(dict, "tree_proof")?)?,
+        root_after: num_from_py(&item(dict, "root_after")?)?,
+        delta: num_from_py(&item(dict, "delta")?)?,
+        out_commit: num_from_py(&item(dict, "out_commit")?)?,
+        nullifier: num_from_py(&item(dict, "nullifier")?)?,
+        memo: bytes_from_py(&item(dict, "memo")?)?,
+        extra_data: bytes_from_py(&item(dict, "extra_data")?)?,
+        asset_id,
+    })
+}
+
+fn proof_to_py<'py>(py: Python<'py>, proof: &Proof<Bn256>) -> PyResult<Bound<'py, PyDict>> {
+    let point =
+        |x: &Num<Fq>, y: &Num<Fq>| -> PyResult<_> { Ok((num_to_py(py, x)?, num_to_py(py, y)?)) };
+
+    let dict = PyDict::new(py);
+    dict.set_item("a", point(&proof.a.0, &proof.a.1)?)?;
+    dict.set_item(
+        "b",
+        (
+            point(&proof.b.0 .0, &proof.b.0 .1)?,
+            point(&proof.b.1 .0, &proof.b.1 .1)?,
+        ),
+    )?;
+    dict.set_item("c", point(&proof.c.0, &proof.c.1)?)?;
+    Ok(dict)
+}
+
+fn proof_from_py(proof: &Bound<'_, PyAny>) -> PyResult<Proof<Bn256>> {
+    type Point = (Num<Fq>, Num<Fq>);
+
+    let point = |point: Bound<'_, PyAny>| -> PyResult<Point> {
+        let (x, y): (Bound<'_, PyAny>, Bound<'_, PyAny>) = point.extract()?;
+        Ok((num_from_py(&x)?, num_from_py(&y)?))
+    };
+
+    let a = point(proof.get_item("a")?)?;
+    let (b0, b1): (Bound<'_, PyAny>, Bound<'_, PyAny>) = proof.get_item("b")?.extract()?;
+    let c = point(proof.get_item("c")?)?;
+
+    Ok(Proof {
+        a: G1Point(a.0, a.1),
+        b: G2Point(point(b0)?, point(b1)?),
+        c: G1Point(c.0, c.1),
+    })
+}
+
+fn num_to_py<'py, T: PrimeField>(py: Python<'py>, num: &Num<T>) -> PyResult<Bound<'py, PyAny>> {
+    py.get_type::<PyInt>().call1((num.to_string(),))
+}
+
+fn num_from_py<T: PrimeField>(num: &Bound<'_, PyAny>) -> PyResult<Num<T>> {
+    if !num.is_instance_of::<PyInt>() {
+        return Err(PyTypeError::new_err("field element must be an int"));
+    }
+
+    num.str()?
+        .to_str()?
+        .parse()
+        .map_err(|_| PyValueError::new_err("invalid field element"))
+}
+
+fn bytes_from_py(bytes: &Bound<'_, PyAny>) -> PyResult<Vec<u8>> {
+    Ok(bytes.cast::<PyBytes>()?.as_bytes().to_vec())
+}
+
+fn item<'py>(dict: &Bound<'py, PyDict>, key: &str) -> PyResult<Bound<'py, PyAny>> {
+    dict.get_item(key)?
+        .ok_or_else(|| PyKeyError::new_err(key.to_owned()))
+}
+
+fn to_py_error(err: Error) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::{sample_tx, zero_proof, TestTxData};
+
+    #[test]
+    fn test_python_decode_encode() {
+        let mut memo = 5u64.to_le_bytes().to_vec();
+        memo.extend_from_slice(&1u64.to_le_bytes());
+        memo.extend_from_slice(&[0; 31]);
+        memo.push(0x20);
+        let mut proof = zero_proof();
+        proof.b.1 .0 = -Num::ONE;
+        let tx = TestTxData {
+            proof,
+            root_after: Num::from(4u64),
+            delta: Delta {
+                token_amount: -10,
+                energy_amount: 0,
+                index: 128,
+                pool_id: 0,
+            }
+            .to_num(),
+            out_commit: Num::from(2u64),
+            nullifier: -Num::ONE,
+            memo,
+            ..sample_tx(TxType::Withdraw)
+        };
+        let mut bytes = vec![];
+        Solana::write(&tx, &mut bytes).unwrap();
+
+        Python::initialize();
+        Python::attach(|py| {
+            let dict = decode(py, "solana", &bytes).unwrap();
+            let nullifier: String = item(&dict, "nullifier").unwrap().str().unwrap().to_string();
+            assert_eq!(nullifier, (-Num::<Fr>::ONE).to_string());
+            assert_eq!(tx_from_py(&dict).unwrap(), tx);
+            assert_eq!(encode(py, "solana", &dict).unwrap().as_bytes(), bytes);
+            assert!(encode(py, "ethereum", &dict).is_err());
+
+            assert_eq!(tx_fee("solana", &dict).unwrap(), 5);
+            let delta = parse_delta(py, &item(&dict, "delta").unwrap()).unwrap();
+            let token_amount: i64 = item(&delta, "token_amount").unwrap().extract().unwrap();
+            assert_eq!(token_amount, -10);
+
+            dict.set_item("nullifier", "1").unwrap();
+            assert!(tx_from_py(&dict).is_err());
+        });
+    }
+}
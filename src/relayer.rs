@@ -0,0 +1,415 @@
+//! Payloads of the relayer HTTP API: `POST /sendTransactions`, `GET /fee`, `GET /info`,
+//! `GET /job/:id` and `GET /transactions/v2`. Only types and validation, no transport.
+//!
+//! Relayers build the tree proof themselves, so a submitted transaction carries the public inputs
+//! of the transaction proof instead of `root_after` and `tree_proof`.
+
+use std::{
+    fmt::{Display, Formatter},
+    io::{Error, ErrorKind, Result},
+    str::FromStr,
+};
+
+use fawkes_crypto::ff_uint::{Num, NumRepr, PrimeField, Uint};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{fee::FeePolicy, hash::keccak256, proof::Proof, AssetId, Codec, TxData, TxType};
+
+/// Hash of the memo as the transaction circuit takes it: keccak256 reduced modulo the field.
+pub fn memo_hash<Fr: PrimeField>(memo: &[u8]) -> Num<Fr> {
+    Num::from_uint_reduced(NumRepr(Fr::Inner::from_big_endian(&keccak256(memo))))
+}
+
+/// A transaction proof with its public inputs: the merkle root the transaction spends from,
+/// `nullifier`, `out_commit`, `delta` and the memo hash, in that order.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ProofWithInputs<Fr: PrimeField, P: Proof> {
+    pub inputs: Vec<Num<Fr>>,
+    pub proof: P,
+}
+
+/// An element of the `POST /sendTransactions` body.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", bound = "")]
+pub struct TransactionRequest<Fr: PrimeField, P: Proof> {
+    pub proof: ProofWithInputs<Fr, P>,
+    #[serde(with = "crate::utils::serde_hex")]
+    pub memo: Vec<u8>,
+    pub tx_type: TxType,
+    /// Signature of the depositor over the nullifier. Only present for deposits.
+    #[serde(default, with = "crate::utils::serde_hex_option")]
+    pub deposit_signature: Option<Vec<u8>>,
+}
+
+impl<Fr: PrimeField, P: Proof> TransactionRequest<Fr, P> {
+    /// Builds a request for a transaction spending from `root`. The deposit signature is taken
+    /// from the extra data of deposits as laid out by `C`.
+    pub fn new<C: Codec>(tx: &TxData<Fr, P>, root: Num<Fr>) -> Result<Self> {
+        let deposit_signature = match tx.tx_type {
+            TxType::Deposit => Some(C::deposit_signature(&tx.extra_data)?.to_vec()),
+            _ => None,
+        };
+
+        Ok(Self {
+            proof: ProofWithInputs {
+                inputs: vec![
+                    root,
+                    tx.nullifier,
+                    tx.out_commit,
+                    tx.delta,
+                    memo_hash(&tx.memo),
+                ],
+                proof: tx.proof.my_clone(),
+            },
+            memo: tx.memo.clone(),
+            tx_type: tx.tx_type,
+            deposit_signature,
+        })
+    }
+
+    /// Checks the shape of the request. The proof itself is left to the verifier.
+    pub fn validate(&self) -> Result<()> {
+        let inputs = &self.proof.inputs;
+
+        if inputs.len() != 5 {
+            return Err(invalid("expected 5 public inputs"));
+        }
+        if inputs[4] != memo_hash(&self.memo) {
+            return Err(invalid("memo hash does not match the memo"));
+        }
+
+        match (self.tx_type, &self.deposit_signature) {
+            (TxType::Deposit, None) => Err(invalid("deposit signature is missing")),
+            (TxType::Deposit, Some(_)) | (_, None) => Ok(()),
+            (_, Some(_)) => Err(invalid("only deposits carry a signature")),
+        }
+    }
+
+    pub fn root(&self) -> Option<Num<Fr>> {
+        self.proof.inputs.first().copied()
+    }
+
+    /// Validates the request and completes it with the relayer's tree update. The deposit
+    /// signature becomes the extra data, which is the whole of it for EVM.
+    pub fn into_tx_data(self, root_after: Num<Fr>, tree_proof: P) -> Result<TxData<Fr, P>> {
+        self.validate()?;
+
+        let inputs = &self.proof.inputs;
+        Ok(TxData {
+            tx_type: self.tx_type,
+            nullifier: inputs[1],
+            out_commit: inputs[2],
+            delta: inputs[3],
+            proof: self.proof.proof,
+            tree_proof,
+            root_after,
+            memo: self.memo,
+            extra_data: self.deposit_signature.unwrap_or_default(),
+            asset_id: AssetId::None,
+        })
+    }
+}
+
+/// Response to `POST /sendTransactions`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendTransactionsResponse {
+    pub job_id: String,
+}
+
+/// Response to `GET /fee`. Amounts are in pool token units, as decimal strings.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeQuote {
+    pub fee: BaseFees,
+    /// Charged per memo byte on top of the base fee.
+    #[serde(with = "crate::utils::serde_string")]
+    pub one_byte_fee: u64,
+    /// Charged on withdrawals that convert part of the amount to the native coin.
+    #[serde(with = "crate::utils::serde_string")]
+    pub native_convert_fee: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaseFees {
+    #[serde(with = "crate::utils::serde_string")]
+    pub deposit: u64,
+    #[serde(with = "crate::utils::serde_string")]
+    pub transfer: u64,
+    #[serde(with = "crate::utils::serde_string")]
+    pub withdraw: u64,
+}
+
+/// Lets a client check its fee against a quote the same way the relayer does. The native
+/// conversion fee isn't included, since it depends on the withdraw data.
+impl<Fr: PrimeField, P: Proof> FeePolicy<Fr, P> for FeeQuote {
+    fn min_fee(&self, tx: &TxData<Fr, P>) -> u64 {
+        let base = match tx.tx_type {
+            TxType::Deposit => self.fee.deposit,
+            TxType::Transfer => self.fee.transfer,
+            TxType::Withdraw => self.fee.withdraw,
+        };
+
+        base.saturating_add(self.one_byte_fee.saturating_mul(tx.memo.len() as u64))
+    }
+}
+
+/// Response to `GET /info`. Optimistic values include transactions that are sent but not mined.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", bound = "")]
+pub struct RelayerInfo<Fr: PrimeField> {
+    pub root: Num<Fr>,
+    pub optimistic_root: Num<Fr>,
+    #[serde(with = "crate::utils::serde_string")]
+    pub delta_index: u64,
+    #[serde(with = "crate::utils::serde_string")]
+    pub optimistic_delta_index: u64,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Waiting,
+    Sent,
+    Completed,
+    Reverted,
+    Failed,
+}
+
+impl JobState {
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            JobState::Completed | JobState::Reverted | JobState::Failed
+        )
+    }
+}
+
+/// Response to `GET /job/:id`. Timestamps are Unix milliseconds.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    pub state: JobState,
+    /// Hashes of the chain transactions sent for the job, in the chain's usual format.
+    pub tx_hash: Option<Vec<String>>,
+    pub failed_reason: Option<String>,
+    pub created_on: u64,
+    pub finished_on: Option<u64>,
+}
+
+impl JobStatus {
+    pub fn validate(&self) -> Result<()> {
+        match (self.state, &self.failed_reason) {
+            (JobState::Failed | JobState::Reverted, None) => {
+                return Err(invalid("failed job without a reason"))
+            }
+            (JobState::Waiting | JobState::Sent | JobState::Completed, Some(_)) => {
+                return Err(invalid("failure reason on a job that didn't fail"))
+            }
+            _ => {}
+        }
+
+        if self.state.is_final() != self.finished_on.is_some() {
+            return Err(invalid("only finished jobs have a finish time"));
+        }
+
+        Ok(())
+    }
+}
+
+/// An element of the `GET /transactions/v2` response, a string made of the state flag (`1` if
+/// mined, `0` if pending), the hex transaction hash, the hex `out_commit` and the hex memo.
+#[derive(Clone, Eq, PartialEq)]
+pub struct TransactionRecord<Fr: PrimeField> {
+    pub mined: bool,
+    pub tx_hash: [u8; 32],
+    pub out_commit: Num<Fr>,
+    pub memo: Vec<u8>,
+}
+
+impl<Fr: PrimeField> TransactionRecord<Fr> {
+    pub fn new<P: Proof>(tx: &TxData<Fr, P>, tx_hash: [u8; 32], mined: bool) -> Self {
+        Self {
+            mined,
+            tx_hash,
+            out_commit: tx.out_commit,
+            memo: tx.memo.clone(),
+        }
+    }
+}
+
+impl<Fr: PrimeField> Display for TransactionRecord<Fr> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut out_commit = [0u8; 32];
+        self.out_commit.to_uint().0.put_big_endian(&mut out_commit);
+
+        write!(
+            f,
+            "{}{}{}{}",
+            u8::from(self.mined),
+            hex::encode(self.tx_hash),
+            hex::encode(out_commit),
+            hex::encode(&self.memo)
+        )
+    }
+}
+
+impl<Fr: PrimeField> FromStr for TransactionRecord<Fr> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mined = match s.get(..1) {
+            Some("1") => true,
+            Some("0") => false,
+            _ => return Err(invalid("invalid transaction state")),
+        };
+
+        let bytes = hex::decode(&s[1..]).map_err(|_| invalid("invalid transaction hex"))?;
+        if bytes.len() < 64 {
+            return Err(Error::from(ErrorKind::UnexpectedEof));
+        }
+
+        let out_commit = Num::from_uint(NumRepr(Fr::Inner::from_big_endian(&bytes[32..64])))
+            .ok_or_else(|| invalid("out_commit exceeds the modulus"))?;
+
+        Ok(Self {
+            mined,
+            tx_hash: bytes[..32].try_into().unwrap(),
+            out_commit,
+            memo: bytes[64..].to_vec(),
+        })
+    }
+}
+
+impl<Fr: PrimeField> Serialize for TransactionRecord<Fr> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, Fr: PrimeField> Deserialize<'de> for TransactionRecord<Fr> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        evm::Evm,
+        utils::tests::{sample_tx, zero_proof, TestFr, TestTxData},
+        waves::Waves,
+    };
+
+    fn tx(tx_type: TxType, extra_data: Vec<u8>) -> TestTxData {
+        TestTxData {
+            root_after: Num::from(4u64),
+            delta: Num::from(3u64),
+            out_commit: Num::from(2u64),
+            nullifier: Num::from(1u64),
+            memo: 100u64.to_be_bytes().to_vec(),
+            extra_data,
+            ..sample_tx(tx_type)
+        }
+    }
+
+    #[test]
+    fn test_transaction_request() {
+        let deposit = tx(TxType::Deposit, vec![9; 64]);
+        let request = TransactionRequest::new::<Evm>(&deposit, Num::from(5u64)).unwrap();
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["txType"], "0000");
+        assert_eq!(json["memo"], "0000000000000064");
+        assert_eq!(json["depositSignature"], "09".repeat(64));
+        assert_eq!(json["proof"]["inputs"][0], "5");
+
+        let request: TransactionRequest<TestFr, _> = serde_json::from_value(json).unwrap();
+        assert_eq!(request.root(), Some(Num::from(5u64)));
+        assert_eq!(
+            request
+                .into_tx_data(deposit.root_after, zero_proof())
+                .unwrap(),
+            deposit
+        );
+
+        let mut request = TransactionRequest::new::<Evm>(&deposit, Num::from(5u64)).unwrap();
+        request.memo.push(0);
+        assert!(request.validate().is_err());
+
+        let mut request = TransactionRequest::new::<Evm>(&deposit, Num::from(5u64)).unwrap();
+        request.deposit_signature = None;
+        assert!(request.validate().is_err());
+
+        let request =
+            TransactionRequest::new::<Evm>(&tx(TxType::Transfer, vec![]), Num::ZERO).unwrap();
+        assert!(request.validate().is_ok());
+        assert!(serde_json::to_value(&request).unwrap()["depositSignature"].is_null());
+    }
+
+    #[test]
+    fn test_transaction_request_waves() {
+        let mut extra_data = vec![1; 32];
+        extra_data.extend_from_slice(&[2; 64]);
+        let deposit = tx(TxType::Deposit, extra_data);
+
+        let request = TransactionRequest::new::<Waves>(&deposit, Num::ZERO).unwrap();
+        assert_eq!(request.deposit_signature, Some(vec![2; 64]));
+
+        let deposit = tx(TxType::Deposit, vec![2; 64]);
+        assert!(TransactionRequest::new::<Waves>(&deposit, Num::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_fee_quote_and_job_status() {
+        let quote: FeeQuote = serde_json::from_str(
+            r#"{"fee":{"deposit":"10","transfer":"20","withdraw":"30"},"oneByteFee":"1","nativeConvertFee":"5"}"#,
+        )
+        .unwrap();
+        let tx = tx(TxType::Transfer, vec![]);
+        assert_eq!(quote.min_fee(&tx), 28);
+        assert_eq!(quote.check::<Evm>(&tx), Ok(100));
+
+        let status: JobStatus = serde_json::from_str(
+            r#"{"state":"failed","txHash":null,"failedReason":"nonce too low","createdOn":1,"finishedOn":2}"#,
+        )
+        .unwrap();
+        assert!(status.state.is_final());
+        assert!(status.validate().is_ok());
+
+        let status = JobStatus {
+            state: JobState::Sent,
+            tx_hash: Some(vec!["0xab".to_owned()]),
+            failed_reason: None,
+            created_on: 1,
+            finished_on: Some(2),
+        };
+        assert!(status.validate().is_err());
+    }
+
+    #[test]
+    fn test_transaction_record() {
+        let record = TransactionRecord::new(&tx(TxType::Transfer, vec![]), [0xab; 32], true);
+
+        let s = record.to_string();
+        assert_eq!(s.len(), 1 + 64 + 64 + 16);
+        assert!(s.starts_with("1abab"));
+        assert_eq!(&s[65..129], format!("{:064x}", 2));
+
+        let records: Vec<TransactionRecord<TestFr>> =
+            serde_json::from_value(serde_json::json!([s, format!("0{}", &s[1..])])).unwrap();
+        assert!(records[0] == record);
+        assert!(!records[1].mined);
+        assert!("2abab".parse::<TransactionRecord<TestFr>>().is_err());
+        assert!(s[..100].parse::<TransactionRecord<TestFr>>().is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use fawkes_crypto::ff_uint::PrimeField;
+
+use crate::{
+    hash::sha256,
+    policy::{NumPolicy, NumReader, NumReport},
+    proof::Proof,
+    utils::{read_borsh_array, write_borsh_array, write_num},
+    withdraw::{unexpected_address, ChainAddress},
+    AssetId, Codec, TxData, TxField, TxType,
+};
+
+// # discriminator       8 bytes
+// # nullifier          32 bytes
+// # outCommit          32 bytes
+// # delta              32 bytes
+// # txProof           256 bytes
+// # rootAfter          32 bytes
+// # treeProof         256 bytes
+// # txType             1 byte
+// # memo               4 bytes length + dynamic bytes
+// # extraData          dynamic bytes
+
+/// Anchor discriminator of the `transact` instruction: `sha256("global:transact")[..8]`.
+pub const DISCRIMINATOR: &[u8] = &[0xd9, 0x95, 0x82, 0x8f, 0xdd, 0x34, 0xfc, 0x77];
+
+/// Maximum size of a serialized Solana transaction, including signatures and account keys.
+pub const PACKET_DATA_SIZE: usize = 1232;
+
+pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
+    read_with_policy(r, NumPolicy::Strict).map(|(data, _)| data)
+}
+
+pub fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+    r: &mut R,
+    policy: NumPolicy,
+) -> Result<(TxData<Fr, P>, NumReport)> {
+    let mut nums = NumReader::new(policy);
+    let mut discriminator = [0u8; 8];
+    r.read_exact(&mut discriminator)?;
+
+    if discriminator != DISCRIMINATOR {
+        return Err(Error::new(ErrorKind::InvalidData, "invalid discriminator"));
+    }
+
+    let nullifier = nums.num::<LittleEndian, _, Fr>(r, TxField::Nullifier)?;
+    let out_commit = nums.num::<LittleEndian, _, Fr>(r, TxField::OutCommit)?;
+    let delta = nums.num::<LittleEndian, _, Fr>(r, TxField::Delta)?;
+    let proof = nums.proof::<LittleEndian, _, P>(r, TxField::Proof)?;
+    let root_after = nums.num::<LittleEndian, _, Fr>(r, TxField::RootAfter)?;
+    let tree_proof = nums.proof::<LittleEndian, _, P>(r, TxField::TreeProof)?;
+    let tx_type = r.read_u8()?;
+
+    let tx_type = TxType::try_from(tx_type as u16)?;
+
+    let memo = read_borsh_array(r)?;
+    let mut extra_data = vec![];
+    r.read_to_end(&mut extra_data)?;
+
+    let data = TxData {
+        tx_type,
+        proof,
+        tree_proof,
+        root_after,
+        delta,
+        out_commit,
+        nullifier,
+        memo,
+        extra_data,
+        asset_id: AssetId::None,
+    };
+
+    Ok((data, nums.finish()?))
+}
+
+pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+    data.asset_id.ensure_none()?;
+
+    w.write_all(DISCRIMINATOR)?;
+    write_num::<LittleEndian, _, Fr>(w, &data.nullifier)?;
+    write_num::<LittleEndian, _, Fr>(w, &data.out_commit)?;
+    write_num::<LittleEndian, _, Fr>(w, &data.delta)?;
+    data.proof.write::<LittleEndian, _>(w)?;
+    write_num::<LittleEndian, _, Fr>(w, &data.root_after)?;
+    data.tree_proof.write::<LittleEndian, _>(w)?;
+    w.write_u8(data.tx_type as u8)?;
+    write_borsh_array(w, &data.memo)?;
+    w.write_all(&data.extra_data)?;
+
+    Ok(())
+}
+
+/// A piece of an instruction payload that doesn't fit into a single transaction. The program
+/// reassembles the payload in a buffer account before executing it.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Chunk<'a> {
+    pub offset: u32,
+    pub data: &'a [u8],
+}
+
+/// Splits `payload` into chunks of at most `max_chunk_size` bytes. `max_chunk_size` is what's
+/// left of [`PACKET_DATA_SIZE`] after signatures, account keys and the upload instruction header.
+/// Fails with `ErrorKind::InvalidInput` if it is zero or an offset doesn't fit into a `u32`.
+pub fn split_payload(payload: &[u8], max_chunk_size: usize) -> Result<Vec<Chunk<'_>>> {
+    if max_chunk_size == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "chunk size must be positive",
+        ));
+    }
+
+    payload
+        .chunks(max_chunk_size)
+        .enumerate()
+        .map(|(i, data)| {
+            let offset = u32::try_from(i * max_chunk_size)
+                .map_err(|_| Error::new(ErrorKind::InvalidInput, "payload too large to chunk"))?;
+            Ok(Chunk { offset, data })
+        })
+        .collect()
+}
+
+/// Reassembles a payload of `len` bytes from chunks in any order. Fails if some part of the
+/// payload is missing or a chunk doesn't fit.
+pub fn join_chunks(len: usize, chunks: &[Chunk<'_>]) -> Result<Vec<u8>> {
+    let mut payload = vec![0u8; len];
+    let mut covered = vec![false; len];
+
+    for chunk in chunks {
+        let start = usize::try_from(chunk.offset).ok();
+        let end = start
+            .and_then(|start| start.checked_add(chunk.data.len()))
+            .filter(|&end| end <= len);
+        let (Some(start), Some(end)) = (start, end) else {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "chunk exceeds payload length",
+            ));
+        };
+
+        payload[start..end].copy_from_slice(chunk.data);
+        covered[start..end].fill(true);
+    }
+
+    if covered.contains(&false) {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "missing payload chunk",
+        ));
+    }
+
+    Ok(payload)
+}
+
+/// Instruction data of the pool's `transact` instruction. Hashed with sha256, since Solana
+/// identifies transactions by signature rather than by hash.
+pub struct Solana;
+
+impl Codec for Solana {
+    type MemoOrder = LittleEndian;
+
+    fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
+        read(r)
+    }
+
+    fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+        r: &mut R,
+        policy: NumPolicy,
+    ) -> Result<(TxData<Fr, P>, NumReport)> {
+        read_with_policy(r, policy)
+    }
+
+    fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+        write(data, w)
+    }
+
+    fn read_withdraw_address<R: Read>(r: &mut R) -> Result<ChainAddress> {
+        let mut address = [0u8; 32];
+        r.read_exact(&mut address)?;
+        Ok(ChainAddress::Solana(address))
+    }
+
+    fn write_withdraw_address<W: Write>(address: &ChainAddress, w: &mut W) -> Result<()> {
+        match address {
+            ChainAddress::Solana(address) => w.write_all(address),
+            _ => Err(unexpected_address(address)),
+        }
+    }
+
+    fn hash(bytes: &[u8]) -> [u8; 32] {
+        sha256(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fawkes_crypto::ff_uint::Num;
+
+    use super::*;
+    use crate::utils::tests::{sample_tx, TestTxData};
+
+    fn tx(tx_type: TxType, memo: Vec<u8>, extra_data: Vec<u8>) -> TestTxData {
+        TestTxData {
+            root_after: Num::from(4u64),
+            delta: Num::from(3u64),
+            out_commit: Num::from(2u64),
+            nullifier: Num::from(1u64),
+            memo,
+            extra_data,
+            ..sample_tx(tx_type)
+        }
+    }
+
+    #[test]
+    fn test_solana_write_read_deposit() {
+        let data = tx(TxType::Deposit, vec![5u8, 6u8], vec![9; 64]);
+
+        let mut buf = vec![];
+        write(&data, &mut buf).unwrap();
+
+        assert_eq!(buf.len(), 8 + 32 * 3 + 256 + 32 + 256 + 1 + 4 + 2 + 64);
+        // Field elements are little-endian.
+        assert_eq!(buf[8], 1);
+        assert_eq!(&buf[9..40], &[0; 31]);
+
+        let data2: TestTxData = read(&mut &buf[..]).unwrap();
+        assert_eq!(data, data2);
+    }
+
+    #[test]
+    fn test_solana_write_read_withdraw() {
+        let mut memo = 1u64.to_le_bytes().to_vec();
+        memo.extend_from_slice(&2u64.to_le_bytes());
+        memo.extend_from_slice(&[7; 32]);
+        let data = tx(TxType::Withdraw, memo, vec![]);
+
+        let mut buf = vec![];
+        write(&data, &mut buf).unwrap();
+        let data2: TestTxData = read(&mut &buf[..]).unwrap();
+
+        assert_eq!(data, data2);
+        assert_eq!(
+            data2.withdraw_data::<Solana>().unwrap().receiver,
+            ChainAddress::Solana([7; 32])
+        );
+
+        let chunks = split_payload(&buf, 500).unwrap();
+        assert_eq!(chunks.len(), buf.len().div_ceil(500));
+        assert_eq!(join_chunks(buf.len(), &chunks).unwrap(), buf);
+        assert!(join_chunks(buf.len(), &chunks[1..]).is_err());
+    }
+
+    #[test]
+    fn test_solana_chunk_bounds() {
+        let payload = [1u8; 10];
+        assert_eq!(
+            split_payload(&payload, 0).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+
+        let past_end = Chunk {
+            offset: 8,
+            data: &payload[..4],
+        };
+        let overflowing = Chunk {
+            offset: u32::MAX,
+            data: &payload,
+        };
+        for chunk in [past_end, overflowing] {
+            assert_eq!(
+                join_chunks(payload.len(), &[chunk]).unwrap_err().kind(),
+                ErrorKind::InvalidData
+            );
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::io::{Error, ErrorKind, Result};
+
+use byteorder::BigEndian;
+use fawkes_crypto::ff_uint::{Num, PrimeField};
+
+use crate::{
+    proof::Proof,
+    utils::{read_num, write_num},
+    AssetId, TxData, TxType,
+};
+
+// Calldata is a flat array of felts:
+// # nullifier          u256 (2 felts)
+// # outCommit          u256 (2 felts)
+// # assetId            1 felt, 0 for the native token
+// # delta              u256 (2 felts)
+// # txProof            words (1 + 2 * ceil(len / 32) felts)
+// # rootAfter          u256 (2 felts)
+// # treeProof          words
+// # txType             1 felt
+// # memo               bytes (1 + ceil(len / 31) felts)
+// # extraData          bytes
+//
+// Field elements don't fit into a felt, so they are split like Cairo's `u256`: the low 128 bits
+// first, then the high 128 bits. Proofs are encoded big-endian as for EVM, prefixed with their
+// length in bytes, and split into 32-byte words that are encoded as `u256`, the last word padded
+// with zeros on the right. For Groth16 every word is exactly one proof coordinate. Memo and extra
+// data are prefixed with their length and packed into 31-byte chunks, each read as a big-endian
+// integer, like the words of Cairo's `ByteArray`.
+
+/// Starknet field element, stored big-endian. Always below the Stark prime.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Felt([u8; 32]);
+
+impl Felt {
+    /// `2^251 + 17 * 2^192 + 1`
+    pub const MODULUS: [u8; 32] = [
+        0x08, 0, 0, 0, 0, 0, 0, 0x11, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0x01,
+    ];
+    pub const ZERO: Felt = Felt([0; 32]);
+
+    pub fn from_be_bytes(bytes: [u8; 32]) -> Option<Self> {
+        (bytes < Self::MODULUS).then_some(Self(bytes))
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    fn from_u128(value: u128) -> Self {
+        let mut bytes = [0u8; 32];
+        bytes[16..].copy_from_slice(&value.to_be_bytes());
+        Self(bytes)
+    }
+
+    /// Returns the value if it fits into `len` bytes.
+    fn to_be_bytes_of_len(self, len: usize) -> Result<Vec<u8>> {
+        let (high, low) = self.0.split_at(32 - len);
+
+        if high.iter().any(|byte| *byte != 0) {
+            return Err(Error::new(ErrorKind::InvalidData, "felt out of range"));
+        }
+
+        Ok(low.to_vec())
+    }
+
+    fn to_len(self) -> Result<usize> {
+        let bytes = self.to_be_bytes_of_len(4)?;
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()) as usize)
+    }
+}
+
+pub fn encode<Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>) -> Result<Vec<Felt>> {
+    let asset_id = match data.asset_id {
+        AssetId::None => Felt::ZERO,
+        AssetId::Id32(id) => Felt::from_be_bytes(id)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "asset id is not a valid felt"))?,
+        _ => data.asset_id.ensure_none().map(|_| Felt::ZERO)?,
+    };
+
+    let mut felts = Vec::new();
+    push_num(&mut felts, &data.nullifier)?;
+    push_num(&mut felts, &data.out_commit)?;
+    felts.push(asset_id);
+    push_num(&mut felts, &data.delta)?;
+    push_proof(&mut felts, &data.proof)?;
+    push_num(&mut felts, &data.root_after)?;
+    push_proof(&mut felts, &data.tree_proof)?;
+    felts.push(Felt::from_u128(data.tx_type as u128));
+    push_bytes(&mut felts, &data.memo);
+    push_bytes(&mut felts, &data.extra_data);
+
+    Ok(felts)
+}
+
+pub fn decode<Fr: PrimeField, P: Proof>(felts: &[Felt]) -> Result<TxData<Fr, P>> {
+    let mut r = FeltReader(felts);
+
+    let nullifier = r.read_num()?;
+    let out_commit = r.read_num()?;
+    let asset_id = AssetId::from_id32(r.next()?.to_be_bytes());
+    let delta = r.read_num()?;
+    let proof = r.read_proof()?;
+    let root_after = r.read_num()?;
+    let tree_proof = r.read_proof()?;
+    let tx_type = r.next()?.to_be_bytes_of_len(2)?;
+    let tx_type = TxType::try_from(u16::from_be_bytes([tx_type[0], tx_type[1]]))?;
+    let memo = r.read_bytes()?;
+    let extra_data = r.read_bytes()?;
+
+    if !r.0.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, "trailing felts"));
+    }
+
+    Ok(TxData {
+        tx_type,
+        proof,
+        tree_proof,
+        root_after,
+        delta,
+        out_commit,
+        nullifier,
+        memo,
+        extra_data,
+        asset_id,
+    })
+}
+
+fn push_u256(felts: &mut Vec<Felt>, word: &[u8]) {
+    let mut bytes = [0u8; 32];
+    bytes[..word.len()].copy_from_slice(word);
+    let (high, low) = bytes.split_at(16);
+
+    felts.push(Felt::from_u128(u128::from_be_bytes(
+        low.try_into().unwrap(),
+    )));
+    felts.push(Felt::from_u128(u128::from_be_bytes(
+        high.try_into().unwrap(),
+    )));
+}
+
+fn push_num<Fr: PrimeField>(felts: &mut Vec<Felt>, num: &Num<Fr>) -> Result<()> {
+    let mut bytes = Vec::with_capacity(32);
+    write_num::<BigEndian, _, Fr>(&mut bytes, num)?;
+    push_u256(felts, &bytes);
+    Ok(())
+}
+
+fn push_proof<P: Proof>(felts: &mut Vec<Felt>, proof: &P) -> Result<()> {
+    let mut bytes = Vec::new();
+    proof.write::<BigEndian, _>(&mut bytes)?;
+
+    felts.push(Felt::from_u128(bytes.len() as u128));
+    for word in bytes.chunks(32) {
+        push_u256(felts, word);
+    }
+
+    Ok(())
+}
+
+fn push_bytes(felts: &mut Vec<Felt>, bytes: &[u8]) {
+    felts.push(Felt::from_u128(bytes.len() as u128));
+    for chunk in bytes.chunks(31) {
+        let mut felt = [0u8; 32];
+        felt[32 - chunk.len()..].copy_from_slice(chunk);
+        felts.push(Felt(felt));
+    }
+}
+
+struct FeltReader<'a>(&'a [Felt]);
+
+impl FeltReader<'_> {
+    fn next(&mut self) -> Result<Felt> {
+        let (felt, rest) = self
+            .0
+            .split_first()
+            .ok_or_else(|| Error::from(ErrorKind::UnexpectedEof))?;
+        self.0 = rest;
+        Ok(*felt)
+    }
+
+    fn read_u256(&mut self) -> Result<[u8; 32]> {
+        let low = self.next()?.to_be_bytes_of_len(16)?;
+        let high = self.next()?.to_be_bytes_of_len(16)?;
+
+        let mut bytes = [0u8; 32];
+        bytes[..16].copy_from_slice(&high);
+        bytes[16..].copy_from_slice(&low);
+        Ok(bytes)
+    }
+
+    fn read_num<Fr: PrimeField>(&mut self) -> Result<Num<Fr>> {
+        read_num::<BigEndian, _, Fr>(&mut &self.read_u256()?[..])
+    }
+
+    fn read_proof<P: Proof>(&mut self) -> Result<P> {
+        let len = self.next()?.to_len()?;
+        let mut bytes = Vec::with_capacity(len.min(self.0.len() * 16));
+        for _ in 0..len.div_ceil(32) {
+            bytes.extend_from_slice(&self.read_u256()?);
+        }
+
+        if bytes[len..].iter().any(|byte| *byte != 0) {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid proof padding"));
+        }
+
+        let mut r = &bytes[..len];
+        let proof = P::read::<BigEndian, _>(&mut r)?;
+        if !r.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid proof length"));
+        }
+
+        Ok(proof)
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>> {
+        let len = self.next()?.to_len()?;
+        let mut bytes = Vec::with_capacity(len.min(self.0.len() * 31));
+        while bytes.len() < len {
+            let chunk_len = (len - bytes.len()).min(31);
+            bytes.extend_from_slice(&self.next()?.to_be_bytes_of_len(chunk_len)?);
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::{sample_tx, zero_proof, TestTxData};
+
+    fn roundtrip(tx_type: TxType, memo: Vec<u8>, extra_data: Vec<u8>) {
+        let mut proof = zero_proof();
+        proof.a.0 = -Num::ONE;
+        let data = TestTxData {
+            proof,
+            root_after: Num::from(4u64),
+            delta: -Num::from(3u64),
+            out_commit: Num::from(2u64),
+            nullifier: Num::from(1u64),
+            memo,
+            extra_data,
+            asset_id: AssetId::Id32([1; 32]),
+            ..sample_tx(tx_type)
+        };
+
+        let felts = encode(&data).unwrap();
+        let proof_len = 1 + 2 * 8;
+        let memo_len = 1 + data.memo.len().div_ceil(31);
+        let extra_data_len = 1 + data.extra_data.len().div_ceil(31);
+        assert_eq!(
+            felts.len(),
+            2 * 4 + 1 + 2 * proof_len + 1 + memo_len + extra_data_len
+        );
+        assert!(felts
+            .iter()
+            .all(|felt| Felt::from_be_bytes(felt.to_be_bytes()).is_some()));
+
+        let data2: TestTxData = decode(&felts).unwrap();
+        assert_eq!(data2, data);
+        assert_eq!(data2.asset_id, data.asset_id);
+
+        let truncated: Result<TestTxData> = decode(&felts[..felts.len() - 1]);
+        assert!(truncated.is_err());
+    }
+
+    #[test]
+    fn test_starknet_roundtrip_deposit() {
+        roundtrip(TxType::Deposit, vec![1; 8], vec![2; 32 + 64]);
+    }
+
+    #[test]
+    fn test_starknet_roundtrip_transfer() {
+        roundtrip(TxType::Transfer, (0..200).collect(), vec![]);
+    }
+
+    #[test]
+    fn test_starknet_roundtrip_withdraw() {
+        roundtrip(TxType::Withdraw, vec![3; 62], vec![]);
+    }
+}
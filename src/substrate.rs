@@ -1,23 +1,34 @@
-use std::io::{Read, Result, Write};
+use std::io::{Error, ErrorKind, Read, Result, Write};
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use fawkes_crypto::ff_uint::PrimeField;
 
 use crate::{
+    hash::blake2b256,
+    policy::{NumPolicy, NumReader, NumReport},
     proof::Proof,
-    utils::{read_num, write_num},
-    TxData, TxType,
+    utils::write_num,
+    withdraw::{unexpected_address, ChainAddress},
+    AssetId, Codec, TxData, TxField, TxType,
 };
 
 pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
-    let nullifier = read_num::<BigEndian, _, Fr>(r)?;
-    let out_commit = read_num::<BigEndian, _, Fr>(r)?;
+    read_with_policy(r, NumPolicy::Strict).map(|(data, _)| data)
+}
+
+pub fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+    r: &mut R,
+    policy: NumPolicy,
+) -> Result<(TxData<Fr, P>, NumReport)> {
+    let mut nums = NumReader::new(policy);
+    let nullifier = nums.num::<BigEndian, _, Fr>(r, TxField::Nullifier)?;
+    let out_commit = nums.num::<BigEndian, _, Fr>(r, TxField::OutCommit)?;
     let mut asset_id = [0u8; 32];
     r.read_exact(&mut asset_id)?;
-    let delta = read_num::<BigEndian, _, Fr>(r)?;
-    let tx_proof = P::read::<BigEndian, _>(r)?;
-    let tree_proof = P::read::<BigEndian, _>(r)?;
-    let root_after = read_num::<BigEndian, _, Fr>(r)?;
+    let delta = nums.num::<BigEndian, _, Fr>(r, TxField::Delta)?;
+    let tx_proof = nums.proof::<BigEndian, _, P>(r, TxField::Proof)?;
+    let tree_proof = nums.proof::<BigEndian, _, P>(r, TxField::TreeProof)?;
+    let root_after = nums.num::<BigEndian, _, Fr>(r, TxField::RootAfter)?;
     let tx_type = r.read_u16::<BigEndian>()?;
 
     let mut memo_data = vec![];
@@ -28,12 +39,15 @@ pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P
     let (memo, extra_data) = if tx_type == TxType::Deposit {
         (memo_data, Vec::new())
     } else {
-        let memo = memo_data[..memo_data.len() - 64 - 32].to_vec();
-        let extra_data = memo_data[memo_data.len() - 64 - 32..].to_vec();
-        (memo, extra_data)
+        let memo_size = memo_data
+            .len()
+            .checked_sub(32 + 64)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "missing signature"))?;
+        let extra_data = memo_data.split_off(memo_size);
+        (memo_data, extra_data)
     };
 
-    Ok(TxData {
+    let data = TxData {
         tx_type,
         proof: tx_proof,
         tree_proof,
@@ -43,13 +57,17 @@ pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P
         nullifier,
         memo,
         extra_data,
-        token_id: String::new(), // FIXME
-    })
+        asset_id: AssetId::from_id32(asset_id),
+    };
+
+    Ok((data, nums.finish()?))
 }
 
 pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
     const SELECTOR: &[u8] = &[0, 0, 0, 0];
 
+    data.asset_id.ensure_none()?;
+
     w.write_all(SELECTOR)?;
     write_num::<BigEndian, _, Fr>(w, &data.nullifier)?;
     write_num::<BigEndian, _, Fr>(w, &data.out_commit)?;
@@ -64,3 +82,130 @@ pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W
 
     Ok(())
 }
+
+/// Substrate pallet call data. Hashed with blake2b-256.
+pub struct Substrate;
+
+impl Codec for Substrate {
+    type MemoOrder = BigEndian;
+
+    fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
+        read(r)
+    }
+
+    fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+        r: &mut R,
+        policy: NumPolicy,
+    ) -> Result<(TxData<Fr, P>, NumReport)> {
+        read_with_policy(r, policy)
+    }
+
+    fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+        write(data, w)
+    }
+
+    fn read_withdraw_address<R: Read>(r: &mut R) -> Result<ChainAddress> {
+        let mut address = [0u8; 32];
+        r.read_exact(&mut address)?;
+        Ok(ChainAddress::Substrate(address))
+    }
+
+    fn write_withdraw_address<W: Write>(address: &ChainAddress, w: &mut W) -> Result<()> {
+        match address {
+            ChainAddress::Substrate(address) => w.write_all(address),
+            _ => Err(unexpected_address(address)),
+        }
+    }
+
+    fn hash(bytes: &[u8]) -> [u8; 32] {
+        blake2b256(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::{collection::vec, prelude::*, sample::Index};
+
+    use super::*;
+    use crate::utils::tests::{
+        arb_deposit_data, arb_tx_data, check_truncated, ArbitraryProof, TestFr, TestProof,
+    };
+
+    fn asset_id() -> BoxedStrategy<AssetId> {
+        prop_oneof![
+            Just(AssetId::None),
+            any::<[u8; 32]>()
+                .prop_filter("a zero asset id means no asset", |id| *id != [0; 32])
+                .prop_map(AssetId::Id32),
+        ]
+        .boxed()
+    }
+
+    fn extra_data(tx_type: TxType) -> BoxedStrategy<Vec<u8>> {
+        // Transfers and withdrawals are signed by the relayer.
+        if tx_type == TxType::Deposit {
+            Just(vec![]).boxed()
+        } else {
+            arb_deposit_data()
+        }
+    }
+
+    /// The call data `read` expects, which differs from what `write` produces.
+    fn call_data<P: ArbitraryProof>(data: &TxData<TestFr, P>) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_num::<BigEndian, _, TestFr>(&mut bytes, &data.nullifier).unwrap();
+        write_num::<BigEndian, _, TestFr>(&mut bytes, &data.out_commit).unwrap();
+        bytes.extend_from_slice(&data.asset_id.to_id32().unwrap());
+        write_num::<BigEndian, _, TestFr>(&mut bytes, &data.delta).unwrap();
+        data.proof.write::<BigEndian, _>(&mut bytes).unwrap();
+        data.tree_proof.write::<BigEndian, _>(&mut bytes).unwrap();
+        write_num::<BigEndian, _, TestFr>(&mut bytes, &data.root_after).unwrap();
+        bytes.write_u16::<BigEndian>(data.tx_type as u16).unwrap();
+        bytes.extend_from_slice(&data.memo);
+        bytes.extend_from_slice(&data.extra_data);
+        bytes
+    }
+
+    fn check_read<P: ArbitraryProof>(
+        tx: &TxData<TestFr, P>,
+        len: Index,
+    ) -> std::result::Result<(), TestCaseError> {
+        let bytes = call_data(tx);
+        let decoded: TxData<TestFr, P> = read(&mut &bytes[..])?;
+        prop_assert_eq!(&decoded, tx);
+
+        let _ = read::<_, TestFr, P>(&mut &bytes[..len.index(bytes.len() + 1)]);
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn test_substrate_read(
+            tx in arb_tx_data::<TestProof>(asset_id, extra_data),
+            len in any::<Index>(),
+        ) {
+            check_read(&tx, len)?;
+        }
+
+        #[test]
+        fn test_substrate_write(
+            tx in arb_tx_data::<TestProof>(asset_id, extra_data),
+            len in any::<Index>(),
+        ) {
+            let mut bytes = vec![];
+            let result = write(&tx, &mut bytes);
+            prop_assert_eq!(result.is_ok(), tx.asset_id == AssetId::None);
+            if result.is_ok() {
+                let tail = [&(tx.memo.len() as u16).to_be_bytes()[..], &tx.memo, &tx.extra_data].concat();
+                prop_assert!(bytes.starts_with(&[0; 4]));
+                prop_assert!(bytes.ends_with(&tail));
+                check_truncated::<Substrate, _>(&tx, len)?;
+            }
+        }
+
+        #[test]
+        fn test_substrate_read_random_bytes(bytes in vec(any::<u8>(), 0..1024)) {
+            let _ = read::<_, TestFr, TestProof>(&mut &bytes[..]);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! TRON deployments run the same Solidity pool contract as EVM chains, so the calldata layout is
+//! identical to [`crate::evm`]. Only withdraw receivers differ: they are 21-byte TRON addresses.
+
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use byteorder::BigEndian;
+use fawkes_crypto::ff_uint::PrimeField;
+
+pub use crate::evm::{
+    read, read_with_policy, read_with_selector, write, write_with_selector, SELECTOR,
+};
+use crate::{
+    hash::sha256,
+    policy::{NumPolicy, NumReport},
+    proof::Proof,
+    withdraw::{unexpected_address, ChainAddress, TRON_ADDRESS_VERSION},
+    Codec, TxData,
+};
+
+/// Encodes a 21-byte TRON address in its base58check form, e.g. `T9yD14Nj9j7xAB4dbGeiX9h8unkKHxuWwb`.
+pub fn address_to_base58(address: &[u8; 21]) -> String {
+    bs58::encode(address).with_check().into_string()
+}
+
+pub fn address_from_base58(address: &str) -> Result<[u8; 21]> {
+    let bytes = bs58::decode(address)
+        .with_check(Some(TRON_ADDRESS_VERSION))
+        .into_vec()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid tron address"))?;
+
+    bytes
+        .try_into()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid tron address length"))
+}
+
+/// Calldata of the pool `transact()` call on TRON. Hashed with sha256.
+pub struct Tron;
+
+impl Codec for Tron {
+    type MemoOrder = BigEndian;
+
+    fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
+        read(r)
+    }
+
+    fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+        r: &mut R,
+        policy: NumPolicy,
+    ) -> Result<(TxData<Fr, P>, NumReport)> {
+        read_with_policy(r, policy)
+    }
+
+    fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+        write(data, w)
+    }
+
+    fn read_withdraw_address<R: Read>(r: &mut R) -> Result<ChainAddress> {
+        let mut address = [0u8; 21];
+        r.read_exact(&mut address)?;
+        Ok(ChainAddress::Tron(address))
+    }
+
+    fn write_withdraw_address<W: Write>(address: &ChainAddress, w: &mut W) -> Result<()> {
+        match address {
+            ChainAddress::Tron(address) => w.write_all(address),
+            _ => Err(unexpected_address(address)),
+        }
+    }
+
+    fn hash(bytes: &[u8]) -> [u8; 32] {
+        sha256(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tron_address_base58() {
+        let address = address_from_base58("TLsV52sRDL79HXGGm9yzwKibb6BeruhUzy").unwrap();
+        assert_eq!(address[0], TRON_ADDRESS_VERSION);
+        assert_eq!(
+            address_to_base58(&address),
+            "TLsV52sRDL79HXGGm9yzwKibb6BeruhUzy"
+        );
+        assert!(address_from_base58("TLsV52sRDL79HXGGm9yzwKibb6BeruhUzz").is_err());
+    }
+}
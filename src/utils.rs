@@ -1,8 +1,10 @@
 use std::io::{Error, ErrorKind, Read, Result, Write};
 
-use byteorder::ByteOrder;
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
 use fawkes_crypto::ff_uint::{Num, NumRepr, PrimeField, Uint};
 
+use crate::policy::NumPolicy;
+
 #[derive(Eq, PartialEq)]
 pub enum Endianness {
     Little,
@@ -22,6 +24,15 @@ impl ByteOrderExt for byteorder::BigEndian {
 }
 
 pub fn read_num<O: ByteOrderExt, R: Read, P: PrimeField>(r: &mut R) -> Result<Num<P>> {
+    read_num_with_policy::<O, _, P>(r, NumPolicy::Strict).map(|(num, _)| num)
+}
+
+/// Also returns whether the element was canonical. Only [`NumPolicy::Strict`] fails on
+/// non-canonical elements, the other policies reduce them.
+pub fn read_num_with_policy<O: ByteOrderExt, R: Read, P: PrimeField>(
+    r: &mut R,
+    policy: NumPolicy,
+) -> Result<(Num<P>, bool)> {
     let mut bytes = [0u8; 32];
     r.read_exact(&mut bytes)?;
 
@@ -31,8 +42,11 @@ pub fn read_num<O: ByteOrderExt, R: Read, P: PrimeField>(r: &mut R) -> Result<Nu
         P::Inner::from_big_endian(&bytes)
     };
 
-    Num::from_uint(NumRepr(uint))
-        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "invalid field element"))
+    match Num::from_uint(NumRepr(uint)) {
+        Some(num) => Ok((num, true)),
+        None if policy != NumPolicy::Strict => Ok((Num::from_uint_reduced(NumRepr(uint)), false)),
+        None => Err(Error::new(ErrorKind::InvalidData, "invalid field element")),
+    }
 }
 
 pub fn write_num<O: ByteOrderExt, W: Write, P: PrimeField>(
@@ -50,3 +64,274 @@ pub fn write_num<O: ByteOrderExt, W: Write, P: PrimeField>(
 
     buf.write_all(&bytes)
 }
+
+/// Reads exactly `len` bytes. Unlike `read_exact`, doesn't allocate a buffer of an untrusted
+/// length upfront.
+pub fn read_bytes<R: Read>(r: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+
+    if buf.len() != len {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+    }
+
+    Ok(buf)
+}
+
+pub fn read_borsh_string<R: Read>(r: &mut R) -> Result<String> {
+    let len = r.read_u32::<LittleEndian>()?;
+    let buf = read_bytes(r, len as usize)?;
+    String::from_utf8(buf).map_err(|_| Error::new(ErrorKind::InvalidData, "invalid utf8"))
+}
+
+pub fn write_borsh_string<W: Write>(w: &mut W, s: &str) -> Result<()> {
+    w.write_u32::<LittleEndian>(s.len() as u32)?;
+    w.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+pub fn read_borsh_array<R: Read>(r: &mut R) -> Result<Vec<u8>> {
+    let len = r.read_u32::<LittleEndian>()?;
+    read_bytes(r, len as usize)
+}
+
+pub fn write_borsh_array<W: Write>(w: &mut W, s: &[u8]) -> Result<()> {
+    w.write_u32::<LittleEndian>(s.len() as u32)?;
+    w.write_all(s)?;
+    Ok(())
+}
+
+/// Serializes bytes as a standard base64 string.
+pub mod serde_base64 {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        STANDARD.decode(s).map_err(D::Error::custom)
+    }
+}
+
+/// Hex without a `0x` prefix, which is accepted but not required when deserializing.
+pub mod serde_hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        hex::decode(s.strip_prefix("0x").unwrap_or(&s)).map_err(D::Error::custom)
+    }
+}
+
+/// [`serde_hex`] for optional values, which are `null` when absent.
+pub mod serde_hex_option {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        bytes: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => super::serde_hex::serialize(bytes, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        #[derive(Deserialize)]
+        struct Hex(#[serde(with = "super::serde_hex")] Vec<u8>);
+
+        Ok(Option::<Hex>::deserialize(deserializer)?.map(|Hex(bytes)| bytes))
+    }
+}
+
+/// Serializes integers as decimal strings, since JSON numbers lose precision above 2^53.
+pub mod serde_string {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::fmt::Debug;
+
+    #[cfg(feature = "groth16")]
+    use fawkes_crypto::backend::bellman_groth16::{
+        engines::{Bn256, Engine},
+        group::{G1Point, G2Point},
+        prover::Proof,
+    };
+    use fawkes_crypto::{
+        engines::bn256::Fr,
+        ff_uint::{Num, NumRepr, PrimeField, Uint},
+    };
+    use proptest::{collection::vec, prelude::*};
+
+    use crate::{AssetId, Codec, TxData, TxType};
+
+    pub type TestFr = Fr;
+    #[cfg(feature = "groth16")]
+    pub type TestProof = Proof<Bn256>;
+    #[cfg(feature = "groth16")]
+    pub type TestTxData = TxData<TestFr, TestProof>;
+
+    #[cfg(feature = "groth16")]
+    pub fn zero_proof<E: Engine>() -> Proof<E> {
+        Proof {
+            a: G1Point(Num::ZERO, Num::ZERO),
+            b: G2Point((Num::ZERO, Num::ZERO), (Num::ZERO, Num::ZERO)),
+            c: G1Point(Num::ZERO, Num::ZERO),
+        }
+    }
+
+    /// A transaction of `tx_type` with zero proofs and field elements and no memo, extra data or
+    /// asset id. Tests set the fields they care about with struct update syntax.
+    #[cfg(feature = "groth16")]
+    pub fn sample_tx(tx_type: TxType) -> TestTxData {
+        TxData {
+            tx_type,
+            proof: zero_proof(),
+            tree_proof: zero_proof(),
+            root_after: Num::ZERO,
+            delta: Num::ZERO,
+            out_commit: Num::ZERO,
+            nullifier: Num::ZERO,
+            memo: vec![],
+            extra_data: vec![],
+            asset_id: AssetId::None,
+        }
+    }
+
+    /// Proofs built from plain values, since the proof types don't implement `Debug`.
+    pub trait ArbitraryProof: crate::proof::Proof + 'static {
+        type Parts: Debug + Clone + 'static;
+
+        fn arbitrary_parts() -> BoxedStrategy<Self::Parts>;
+        fn from_parts(parts: Self::Parts) -> Self;
+    }
+
+    #[cfg(feature = "groth16")]
+    impl ArbitraryProof for Proof<Bn256> {
+        type Parts = [Num<<Bn256 as Engine>::Fq>; 8];
+
+        fn arbitrary_parts() -> BoxedStrategy<Self::Parts> {
+            proptest::array::uniform8(arb_num()).boxed()
+        }
+
+        fn from_parts([a0, a1, b0, b1, b2, b3, c0, c1]: Self::Parts) -> Self {
+            Proof {
+                a: G1Point(a0, a1),
+                b: G2Point((b0, b1), (b2, b3)),
+                c: G1Point(c0, c1),
+            }
+        }
+    }
+
+    pub fn arb_num<Fp: PrimeField>() -> impl Strategy<Value = Num<Fp>> {
+        any::<[u8; 32]>()
+            .prop_map(|bytes| Num::from_uint_reduced(NumRepr(Fp::Inner::from_big_endian(&bytes))))
+    }
+
+    pub fn arb_tx_type() -> impl Strategy<Value = TxType> {
+        prop_oneof![
+            Just(TxType::Deposit),
+            Just(TxType::Transfer),
+            Just(TxType::Withdraw),
+        ]
+    }
+
+    pub fn arb_memo() -> impl Strategy<Value = Vec<u8>> {
+        vec(any::<u8>(), 0..512)
+    }
+
+    /// Public key and signature of the depositor, or of the relayer on Substrate.
+    pub fn arb_deposit_data() -> BoxedStrategy<Vec<u8>> {
+        vec(any::<u8>(), 32 + 64).boxed()
+    }
+
+    /// Arbitrary transactions with the asset ids and extra data that a codec can represent.
+    pub fn arb_tx_data<P: ArbitraryProof>(
+        asset_id: fn() -> BoxedStrategy<AssetId>,
+        extra_data: fn(TxType) -> BoxedStrategy<Vec<u8>>,
+    ) -> impl Strategy<Value = TxData<TestFr, P>> {
+        arb_tx_type().prop_flat_map(move |tx_type| {
+            (
+                (P::arbitrary_parts(), P::arbitrary_parts()),
+                (arb_num(), arb_num(), arb_num(), arb_num()),
+                arb_memo(),
+                extra_data(tx_type),
+                asset_id(),
+            )
+                .prop_map(
+                    move |(
+                        (proof, tree_proof),
+                        (root_after, delta, out_commit, nullifier),
+                        memo,
+                        extra_data,
+                        asset_id,
+                    )| TxData {
+                        tx_type,
+                        proof: P::from_parts(proof),
+                        tree_proof: P::from_parts(tree_proof),
+                        root_after,
+                        delta,
+                        out_commit,
+                        nullifier,
+                        memo,
+                        extra_data,
+                        asset_id,
+                    },
+                )
+        })
+    }
+
+    /// Checks that `read(write(tx)) == tx`.
+    pub fn check_roundtrip<C: Codec, P: ArbitraryProof>(
+        tx: &TxData<TestFr, P>,
+    ) -> Result<(), TestCaseError> {
+        let mut bytes = Vec::new();
+        C::write(tx, &mut bytes)?;
+        let decoded: TxData<TestFr, P> = C::read(&mut &bytes[..])?;
+
+        prop_assert_eq!(&decoded, tx);
+        Ok(())
+    }
+
+    /// Decodes a prefix of a valid encoding, which must fail or succeed without panicking.
+    pub fn check_truncated<C: Codec, P: ArbitraryProof>(
+        tx: &TxData<TestFr, P>,
+        len: prop::sample::Index,
+    ) -> Result<(), TestCaseError> {
+        let mut bytes = Vec::new();
+        C::write(tx, &mut bytes)?;
+        let _ = C::read::<_, TestFr, P>(&mut &bytes[..len.index(bytes.len() + 1)]);
+        Ok(())
+    }
+}
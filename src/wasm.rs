@@ -0,0 +1,239 @@
+//! `wasm-bindgen` bindings for web wallets. Transactions cross the boundary as plain objects in
+//! the JSON representation of [`crate::json`], the same one `zeropool-tx decode --json` prints.
+//! Its TypeScript types are generated from `schema/tx.schema.json` by the build script.
+//!
+//! Build with `cargo rustc --release --target wasm32-unknown-unknown --features wasm
+//! --crate-type cdylib`, then run `wasm-bindgen` on the output.
+
+use std::io::{Error, ErrorKind};
+
+use fawkes_crypto::{
+    backend::bellman_groth16::{
+        engines::{Bn256, Engine},
+        prover::Proof,
+    },
+    engines::bn256::Fr,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    cosmwasm::CosmWasm,
+    delta::Delta,
+    evm::Evm,
+    json::{BytesEncoding, DeltaJson, HexNum, MemoJson, WithdrawJson},
+    near::Near,
+    solana::Solana,
+    substrate::Substrate,
+    tron::Tron,
+    waves::Waves,
+    Codec,
+};
+
+type TxData = crate::TxData<Fr, Proof<Bn256>>;
+type TxJson = crate::json::TxJson<Fr, <Bn256 as Engine>::Fq>;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &str = include_str!(concat!(env!("OUT_DIR"), "/tx.d.ts"));
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_CHAIN: &str = r#"
+export type Chain = "evm" | "near" | "substrate" | "waves" | "solana" | "tron" | "cosmwasm";
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Chain")]
+    pub type JsChain;
+    #[wasm_bindgen(typescript_type = "Tx")]
+    pub type JsTxData;
+    #[wasm_bindgen(typescript_type = "Delta")]
+    pub type JsDelta;
+    #[wasm_bindgen(typescript_type = "Withdraw")]
+    pub type JsWithdrawData;
+    #[wasm_bindgen(typescript_type = "Memo")]
+    pub type JsMemo;
+}
+
+/// Runs `$body` with `$codec` bound to the codec type of the chain named `$chain`.
+macro_rules! with_codec {
+    ($chain:expr, $codec:ident => $body:expr) => {
+        match $chain.as_string().as_deref() {
+            Some("evm") => {
+                type $codec = Evm;
+                $body
+            }
+            Some("near") => {
+                type $codec = Near;
+                $body
+            }
+            Some("substrate") => {
+                type $codec = Substrate;
+                $body
+            }
+            Some("waves") => {
+                type $codec = Waves;
+                $body
+            }
+            Some("solana") => {
+                type $codec = Solana;
+                $body
+            }
+            Some("tron") => {
+                type $codec = Tron;
+                $body
+            }
+            Some("cosmwasm") => {
+                type $codec = CosmWasm;
+                $body
+            }
+            _ => Err(JsError::new("unknown chain")),
+        }
+    };
+}
+
+#[wasm_bindgen(js_name = encodeTx)]
+pub fn encode_tx(chain: &JsChain, tx: &JsTxData) -> Result<Vec<u8>, JsError> {
+    let tx = tx_from_js(tx)?;
+    let mut bytes = Vec::new();
+    with_codec!(chain, C => C::write(&tx, &mut bytes).map_err(to_js_error))?;
+    Ok(bytes)
+}
+
+/// Decodes a transaction, including its decoded delta and memo.
+#[wasm_bindgen(js_name = decodeTx)]
+pub fn decode_tx(chain: &JsChain, bytes: &[u8]) -> Result<JsTxData, JsError> {
+    with_codec!(chain, C => {
+        let tx: TxData = C::read(&mut &bytes[..]).map_err(to_js_error)?;
+        to_js(&TxJson::new::<C, _>(&tx, BytesEncoding::Hex))
+    })
+}
+
+/// Chain-independent transaction id, see [`crate::TxData::id`].
+#[wasm_bindgen(js_name = txId)]
+pub fn tx_id(tx: &JsTxData) -> Result<Vec<u8>, JsError> {
+    Ok(tx_from_js(tx)?.id().to_vec())
+}
+
+#[wasm_bindgen(js_name = parseDelta)]
+pub fn parse_delta(delta: &str) -> Result<JsDelta, JsError> {
+    let delta: HexNum<Fr> = serde_json::from_value(delta.into())?;
+    let delta = Delta::parse(&delta.0).ok_or_else(|| JsError::new("invalid delta"))?;
+    to_js(&DeltaJson::from(delta))
+}
+
+#[wasm_bindgen(js_name = txFee)]
+pub fn tx_fee(chain: &JsChain, tx: &JsTxData) -> Result<u64, JsError> {
+    let tx = tx_from_js(tx)?;
+    with_codec!(chain, C => tx.fee::<C>().map_err(to_js_error))
+}
+
+#[wasm_bindgen(js_name = withdrawData)]
+pub fn withdraw_data(chain: &JsChain, tx: &JsTxData) -> Result<JsWithdrawData, JsError> {
+    let tx = tx_from_js(tx)?;
+    let data = with_codec!(chain, C => tx.withdraw_data::<C>().map_err(to_js_error))?;
+    to_js(&WithdrawJson::from(data))
+}
+
+/// Parses the memo with the default [`crate::memo::MemoLayout`].
+#[wasm_bindgen(js_name = parseMemo)]
+pub fn parse_memo(chain: &JsChain, tx: &JsTxData) -> Result<JsMemo, JsError> {
+    let tx = tx_from_js(tx)?;
+    let memo = with_codec!(chain, C => {
+        MemoJson::<Fr>::new::<C, _>(&tx, BytesEncoding::Hex).map_err(to_js_error)
+    })?;
+    to_js(&memo)
+}
+
+fn to_js_error(err: Error) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+fn tx_from_js(value: &JsValue) -> Result<TxData, JsError> {
+    from_js::<TxJson>(value)?.to_tx_data().map_err(to_js_error)
+}
+
+// Objects go through JSON so that they match the serde_json representation exactly.
+fn from_js<T: DeserializeOwned>(value: &JsValue) -> Result<T, JsError> {
+    let json = js_sys::JSON::stringify(value)
+        .map_err(|_| JsError::new("transaction is not serializable"))?;
+    let json = json
+        .as_string()
+        .ok_or_else(|| to_js_error(Error::from(ErrorKind::InvalidInput)))?;
+
+    Ok(serde_json::from_str(&json)?)
+}
+
+fn to_js<T: Serialize, R: JsCast>(value: &T) -> Result<R, JsError> {
+    let json = serde_json::to_string(value)?;
+    let value = js_sys::JSON::parse(&json).map_err(|_| JsError::new("invalid json"))?;
+    Ok(value.unchecked_into())
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use fawkes_crypto::ff_uint::Num;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+    use crate::{
+        utils::tests::{sample_tx, TestTxData},
+        TxType,
+    };
+
+    fn chain(name: &str) -> JsChain {
+        JsValue::from_str(name).unchecked_into()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_encode_decode() {
+        let mut memo = 5u64.to_be_bytes().to_vec();
+        memo.extend_from_slice(&1u64.to_be_bytes());
+        memo.extend_from_slice(&[7; 20]);
+        let tx = TestTxData {
+            root_after: Num::from(4u64),
+            delta: Delta {
+                token_amount: -10,
+                energy_amount: 0,
+                index: 128,
+                pool_id: 0,
+            }
+            .to_num(),
+            out_commit: Num::from(2u64),
+            nullifier: Num::from(1u64),
+            memo,
+            ..sample_tx(TxType::Withdraw)
+        };
+
+        let js_tx: JsTxData = to_js(&TxJson::new::<Evm, _>(&tx, BytesEncoding::Hex)).unwrap();
+        let bytes = encode_tx(&chain("evm"), &js_tx).unwrap();
+        let decoded = decode_tx(&chain("evm"), &bytes).unwrap();
+        assert_eq!(tx_from_js(&decoded).unwrap(), tx);
+        assert_eq!(tx_id(&decoded).unwrap(), tx.id());
+        assert!(decode_tx(&chain("ethereum"), &bytes).is_err());
+
+        let json = js_sys::JSON::stringify(&decoded)
+            .unwrap()
+            .as_string()
+            .unwrap();
+        assert!(json.contains(r#""tx_type":"withdraw""#));
+        assert!(json.contains(r#""delta_decoded":{"token_amount":"-10""#));
+
+        assert_eq!(tx_fee(&chain("evm"), &decoded).unwrap(), 5);
+        let data = withdraw_data(&chain("evm"), &decoded).unwrap();
+        assert_eq!(
+            js_sys::JSON::stringify(&data).unwrap(),
+            format!(
+                r#"{{"native_amount":"1","receiver":"0x{}"}}"#,
+                "07".repeat(20)
+            )
+        );
+        assert!(parse_memo(&chain("evm"), &decoded).is_err());
+
+        let delta = parse_delta(&HexNum(tx.delta).to_string()).unwrap();
+        assert_eq!(
+            js_sys::JSON::stringify(&delta).unwrap(),
+            r#"{"token_amount":"-10","energy_amount":"0","index":128,"pool_id":0}"#
+        );
+    }
+}
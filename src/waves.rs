@@ -1,14 +1,19 @@
-use std::io::{Read, Result, Write};
+use std::io::{Error, ErrorKind, Read, Result, Write};
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use fawkes_crypto::ff_uint::{Num, PrimeField};
+use fawkes_crypto::ff_uint::PrimeField;
 
 use crate::{
+    hash::blake2b256,
+    policy::{NumPolicy, NumReader, NumReport},
     proof::Proof,
-    utils::{read_num, write_num},
-    TxData, TxType,
+    utils::write_num,
+    withdraw::{unexpected_address, ChainAddress},
+    AssetId, Codec, TxData, TxField, TxType,
 };
 
+pub mod invoke;
+
 // # nullifier          32 bytes
 // # outCommit         32 bytes
 // # assetId           32 bytes
@@ -25,14 +30,26 @@ use crate::{
 // # depositPk          optional 32 bytes
 // # depositSignature   optional 64 bytes
 
+/// Size of `depositPk` and `depositSignature`, present for deposits.
+const DEPOSIT_DATA_SIZE: usize = 32 + 64;
+
 pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
-    let nullifier = read_num::<BigEndian, _, Fr>(r)?;
-    let out_commit = read_num::<BigEndian, _, Fr>(r)?;
-    let _asset_id = read_num::<BigEndian, _, Fr>(r)?;
-    let delta = read_num::<BigEndian, _, Fr>(r)?;
-    let proof = P::read::<BigEndian, _>(r)?;
-    let tree_proof = P::read::<BigEndian, _>(r)?;
-    let root_after = read_num::<BigEndian, _, Fr>(r)?;
+    read_with_policy(r, NumPolicy::Strict).map(|(data, _)| data)
+}
+
+pub fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+    r: &mut R,
+    policy: NumPolicy,
+) -> Result<(TxData<Fr, P>, NumReport)> {
+    let mut nums = NumReader::new(policy);
+    let nullifier = nums.num::<BigEndian, _, Fr>(r, TxField::Nullifier)?;
+    let out_commit = nums.num::<BigEndian, _, Fr>(r, TxField::OutCommit)?;
+    let mut asset_id = [0u8; 32];
+    r.read_exact(&mut asset_id)?;
+    let delta = nums.num::<BigEndian, _, Fr>(r, TxField::Delta)?;
+    let proof = nums.proof::<BigEndian, _, P>(r, TxField::Proof)?;
+    let tree_proof = nums.proof::<BigEndian, _, P>(r, TxField::TreeProof)?;
+    let root_after = nums.num::<BigEndian, _, Fr>(r, TxField::RootAfter)?;
     let tx_type = r.read_u16::<BigEndian>()?;
 
     let tx_type = TxType::try_from(tx_type)?;
@@ -40,8 +57,10 @@ pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P
     let (memo, extra_data) = if tx_type == TxType::Deposit {
         let mut buf = vec![];
         r.read_to_end(&mut buf)?;
-        let deposit_data_size = 32 + 64;
-        let memo_size = buf.len() - deposit_data_size;
+        let memo_size = buf
+            .len()
+            .checked_sub(DEPOSIT_DATA_SIZE)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "missing deposit data"))?;
         let memo = buf[..memo_size].to_vec();
         let extra_data = buf[memo_size..].to_vec();
         (memo, extra_data)
@@ -51,7 +70,7 @@ pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P
         (memo, vec![])
     };
 
-    Ok(TxData {
+    let data = TxData {
         nullifier,
         out_commit,
         delta,
@@ -61,14 +80,18 @@ pub fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P
         tx_type,
         memo,
         extra_data,
-        token_id: String::new(),
-    })
+        asset_id: AssetId::from_id32(asset_id),
+    };
+
+    Ok((data, nums.finish()?))
 }
 
 pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+    let asset_id = data.asset_id.to_id32()?;
+
     write_num::<BigEndian, _, _>(w, &data.nullifier)?;
     write_num::<BigEndian, _, Fr>(w, &data.out_commit)?;
-    write_num::<BigEndian, _, Fr>(w, &Num::<Fr>::ZERO)?; // TODO: Change once support for different asset ids is added
+    w.write_all(&asset_id)?;
     write_num::<BigEndian, _, Fr>(w, &data.delta)?;
     data.proof.write::<BigEndian, _>(w)?;
     data.tree_proof.write::<BigEndian, _>(w)?;
@@ -80,31 +103,80 @@ pub fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W
     Ok(())
 }
 
+/// Waves pool invocation argument. Hashed with blake2b-256.
+pub struct Waves;
+
+impl Codec for Waves {
+    type MemoOrder = BigEndian;
+
+    fn read<R: Read, Fr: PrimeField, P: Proof>(r: &mut R) -> Result<TxData<Fr, P>> {
+        read(r)
+    }
+
+    fn read_with_policy<R: Read, Fr: PrimeField, P: Proof>(
+        r: &mut R,
+        policy: NumPolicy,
+    ) -> Result<(TxData<Fr, P>, NumReport)> {
+        read_with_policy(r, policy)
+    }
+
+    fn write<W: Write, Fr: PrimeField, P: Proof>(data: &TxData<Fr, P>, w: &mut W) -> Result<()> {
+        write(data, w)
+    }
+
+    fn read_withdraw_address<R: Read>(r: &mut R) -> Result<ChainAddress> {
+        let mut address = [0u8; 26];
+        r.read_exact(&mut address)?;
+        Ok(ChainAddress::Waves(address))
+    }
+
+    fn write_withdraw_address<W: Write>(address: &ChainAddress, w: &mut W) -> Result<()> {
+        match address {
+            ChainAddress::Waves(address) => w.write_all(address),
+            _ => Err(unexpected_address(address)),
+        }
+    }
+
+    fn hash(bytes: &[u8]) -> [u8; 32] {
+        blake2b256(bytes)
+    }
+
+    /// Deposit extra data is the depositor's 32 byte public key followed by the signature.
+    fn deposit_signature(extra_data: &[u8]) -> Result<&[u8]> {
+        if extra_data.len() != DEPOSIT_DATA_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "deposit data must be a public key and a signature",
+            ));
+        }
+
+        Ok(&extra_data[32..])
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use fawkes_crypto::backend::bellman_groth16::{
-        engines::Bn256,
-        group::{G1Point, G2Point},
-        prover::Proof,
-    };
+    use fawkes_crypto::ff_uint::Num;
+    use proptest::{collection::vec, prelude::*, sample::Index};
 
     use super::*;
+    use crate::utils::tests::{
+        arb_deposit_data, arb_tx_data, check_roundtrip, check_truncated, sample_tx, TestFr,
+        TestProof, TestTxData,
+    };
 
     #[test]
     fn test_waves_write_read_deposit() {
         use std::io::Cursor;
 
-        let data = TxData::<Bn256> {
+        let data = TestTxData {
             nullifier: Num::from(1u64),
             out_commit: Num::from(2u64),
             delta: Num::from(3u64),
-            proof: zero_proof(),
             root_after: Num::from(4u64),
-            tree_proof: zero_proof(),
-            tx_type: TxType::Deposit,
             memo: vec![5u8, 6u8],
             extra_data: vec![9; 32 + 64],
-            token_id: String::new(),
+            ..sample_tx(TxType::Deposit)
         };
 
         let mut buf = vec![];
@@ -116,7 +188,7 @@ mod tests {
         );
 
         let mut cursor = Cursor::new(buf);
-        let data2 = read::<_, Bn256>(&mut cursor).unwrap();
+        let data2 = read::<_, TestFr, TestProof>(&mut cursor).unwrap();
 
         assert_eq!(data, data2);
     }
@@ -125,17 +197,13 @@ mod tests {
     fn test_waves_write_read() {
         use std::io::Cursor;
 
-        let data = TxData::<Bn256> {
+        let data = TestTxData {
             nullifier: Num::from(1u64),
             out_commit: Num::from(2u64),
             delta: Num::from(3u64),
-            proof: zero_proof(),
             root_after: Num::from(4u64),
-            tree_proof: zero_proof(),
-            tx_type: TxType::Transfer,
             memo: vec![5u8, 6u8],
-            extra_data: vec![],
-            token_id: String::new(),
+            ..sample_tx(TxType::Transfer)
         };
 
         let mut buf = vec![];
@@ -144,16 +212,42 @@ mod tests {
         assert_eq!(buf.len(), 32 + 32 + 32 + 32 + 256 + 256 + 32 + 2 + 2);
 
         let mut cursor = Cursor::new(buf);
-        let data2 = read::<_, Bn256>(&mut cursor).unwrap();
+        let data2 = read::<_, TestFr, TestProof>(&mut cursor).unwrap();
 
         assert_eq!(data, data2);
     }
 
-    fn zero_proof<E: Engine>() -> Proof<E> {
-        Proof {
-            a: G1Point(Num::ZERO, Num::ZERO),
-            b: G2Point((Num::ZERO, Num::ZERO), (Num::ZERO, Num::ZERO)),
-            c: G1Point(Num::ZERO, Num::ZERO),
+    fn asset_id() -> BoxedStrategy<AssetId> {
+        prop_oneof![
+            Just(AssetId::None),
+            any::<[u8; 32]>()
+                .prop_filter("a zero asset id means no asset", |id| *id != [0; 32])
+                .prop_map(AssetId::Id32),
+        ]
+        .boxed()
+    }
+
+    fn extra_data(tx_type: TxType) -> BoxedStrategy<Vec<u8>> {
+        if tx_type == TxType::Deposit {
+            arb_deposit_data()
+        } else {
+            Just(vec![]).boxed()
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_waves_roundtrip(
+            tx in arb_tx_data::<TestProof>(asset_id, extra_data),
+            len in any::<Index>(),
+        ) {
+            check_roundtrip::<Waves, _>(&tx)?;
+            check_truncated::<Waves, _>(&tx, len)?;
+        }
+
+        #[test]
+        fn test_waves_read_random_bytes(bytes in vec(any::<u8>(), 0..1024)) {
+            let _ = read::<_, TestFr, TestProof>(&mut &bytes[..]);
         }
     }
 }
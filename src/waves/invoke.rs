@@ -0,0 +1,300 @@
+use std::io::{Error, ErrorKind, Result};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use fawkes_crypto::ff_uint::PrimeField;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{delta::Delta, proof::Proof, TxData, TxType};
+
+/// Function the pool dApp exposes for transactions, unless configured otherwise.
+pub const DEFAULT_FUNCTION: &str = "transact";
+
+/// Call part of a Waves InvokeScript transaction, in the JSON form accepted by the node API and
+/// Waves Signer.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvokeScript {
+    /// Base58 address of the pool dApp.
+    pub d_app: String,
+    pub call: FunctionCall,
+    pub payment: Vec<Payment>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub function: String,
+    pub args: Vec<Arg>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum Arg {
+    Binary(#[serde(with = "serde_binary")] Vec<u8>),
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+}
+
+/// Attached payment. Deposits attach the deposited amount of the pool asset.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Payment {
+    pub amount: u64,
+    /// `None` for WAVES.
+    #[serde(with = "serde_asset_id")]
+    pub asset_id: Option<[u8; 32]>,
+}
+
+impl InvokeScript {
+    /// Builds a call of `function` on `d_app` with the transaction as its only `ByteVector`
+    /// argument. Deposits attach their token amount of the transaction's asset.
+    pub fn new<Fr: PrimeField, P: Proof>(
+        data: &TxData<Fr, P>,
+        d_app: &str,
+        function: &str,
+    ) -> Result<Self> {
+        let mut arg = Vec::new();
+        super::write(data, &mut arg)?;
+        let payment = Payment::of(data).map_err(|msg| Error::new(ErrorKind::InvalidInput, msg))?;
+
+        Ok(Self {
+            d_app: d_app.to_owned(),
+            call: FunctionCall {
+                function: function.to_owned(),
+                args: vec![Arg::Binary(arg)],
+            },
+            payment,
+        })
+    }
+
+    /// Extracts the transaction, failing if the call is not to `function` or the payments don't
+    /// match the ones [`InvokeScript::new`] attaches.
+    pub fn tx_data<Fr: PrimeField, P: Proof>(&self, function: &str) -> Result<TxData<Fr, P>> {
+        if self.call.function != function {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unexpected function {}", self.call.function),
+            ));
+        }
+
+        let data = match &self.call.args[..] {
+            [Arg::Binary(arg)] => super::read(&mut &arg[..])?,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "expected a single binary argument",
+                ))
+            }
+        };
+
+        let payment = Payment::of(&data).map_err(|msg| Error::new(ErrorKind::InvalidData, msg))?;
+        if self.payment != payment {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "payments don't match the transaction",
+            ));
+        }
+
+        Ok(data)
+    }
+}
+
+impl Payment {
+    /// Payments a call with `data` attaches: the token amount of the delta for deposits, nothing
+    /// otherwise.
+    fn of<Fr: PrimeField, P: Proof>(
+        data: &TxData<Fr, P>,
+    ) -> std::result::Result<Vec<Self>, &'static str> {
+        if data.tx_type != TxType::Deposit {
+            return Ok(vec![]);
+        }
+
+        let delta = Delta::parse(&data.delta).ok_or("invalid delta")?;
+        let amount = u64::try_from(delta.token_amount)
+            .ok()
+            .filter(|&amount| amount > 0)
+            .ok_or("deposit amount must be positive")?;
+        let asset_id = data
+            .asset_id
+            .to_id32()
+            .map_err(|_| "unsupported asset id")?;
+
+        Ok(vec![Payment {
+            amount,
+            asset_id: (asset_id != [0; 32]).then_some(asset_id),
+        }])
+    }
+}
+
+/// Binary arguments are base64 strings prefixed with `base64:`.
+mod serde_binary {
+    use super::*;
+
+    const PREFIX: &str = "base64:";
+
+    pub fn serialize<S: Serializer>(
+        bytes: &[u8],
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{PREFIX}{}", STANDARD.encode(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let s = s.strip_prefix(PREFIX).unwrap_or(&s);
+        STANDARD.decode(s).map_err(de::Error::custom)
+    }
+}
+
+/// Asset ids are base58 strings, or `null` for WAVES.
+mod serde_asset_id {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        asset_id: &Option<[u8; 32]>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match asset_id {
+            Some(asset_id) => serializer.serialize_str(&bs58::encode(asset_id).into_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Option<[u8; 32]>, D::Error> {
+        let Some(s) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+
+        let mut asset_id = [0u8; 32];
+        let len = bs58::decode(&s)
+            .onto(&mut asset_id)
+            .map_err(de::Error::custom)?;
+        if len != asset_id.len() {
+            return Err(de::Error::custom("asset id must be 32 bytes"));
+        }
+
+        Ok(Some(asset_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fawkes_crypto::ff_uint::Num;
+
+    use super::*;
+    use crate::{
+        utils::tests::{sample_tx, TestFr, TestProof, TestTxData},
+        AssetId, TxType,
+    };
+
+    #[test]
+    fn test_invoke_script_roundtrip() {
+        let data = TestTxData {
+            delta: Num::from(100u64),
+            nullifier: Num::from(1u64),
+            memo: vec![1, 2, 3],
+            extra_data: vec![7; 32 + 64],
+            asset_id: AssetId::Id32([0xab; 32]),
+            ..sample_tx(TxType::Deposit)
+        };
+        let asset_id = [0xab; 32];
+
+        let invoke = InvokeScript::new(
+            &data,
+            "3PEktVux2RhchSN63DsDo4b4mz4QqzKSeDv",
+            DEFAULT_FUNCTION,
+        )
+        .unwrap();
+        assert_eq!(
+            invoke.payment,
+            [Payment {
+                amount: 100,
+                asset_id: Some(asset_id),
+            }]
+        );
+
+        let json = serde_json::to_value(&invoke).unwrap();
+        assert_eq!(json["call"]["args"][0]["type"], "binary");
+        assert!(json["call"]["args"][0]["value"]
+            .as_str()
+            .unwrap()
+            .starts_with("base64:"));
+        assert_eq!(
+            json["payment"][0]["assetId"],
+            bs58::encode(asset_id).into_string()
+        );
+
+        let invoke2: InvokeScript = serde_json::from_value(json).unwrap();
+        assert_eq!(invoke2, invoke);
+
+        let data2 = invoke2.tx_data(DEFAULT_FUNCTION).unwrap();
+        assert_eq!(data2, data);
+        assert_eq!(data2.asset_id, data.asset_id);
+
+        let waves_payment: Payment =
+            serde_json::from_str(r#"{"amount": 5, "assetId": null}"#).unwrap();
+        assert_eq!(waves_payment.asset_id, None);
+    }
+
+    #[test]
+    fn test_invoke_script_payment() {
+        let deposit = TestTxData {
+            delta: Num::from(100u64),
+            nullifier: Num::from(1u64),
+            memo: vec![1, 2, 3],
+            extra_data: vec![7; 32 + 64],
+            ..sample_tx(TxType::Deposit)
+        };
+        let invoke = InvokeScript::new(&deposit, "3P", DEFAULT_FUNCTION).unwrap();
+        assert_eq!(
+            invoke.payment,
+            [Payment {
+                amount: 100,
+                asset_id: None,
+            }]
+        );
+
+        let mut tampered = invoke.clone();
+        tampered.payment[0].amount = 99;
+        let err = tampered
+            .tx_data::<TestFr, TestProof>(DEFAULT_FUNCTION)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        tampered.payment.clear();
+        assert!(tampered
+            .tx_data::<TestFr, TestProof>(DEFAULT_FUNCTION)
+            .is_err());
+
+        let transfer = TestTxData {
+            tx_type: TxType::Transfer,
+            extra_data: vec![],
+            ..deposit.clone()
+        };
+        let mut invoke = InvokeScript::new(&transfer, "3P", DEFAULT_FUNCTION).unwrap();
+        assert!(invoke.payment.is_empty());
+        invoke.payment = vec![Payment {
+            amount: 100,
+            asset_id: None,
+        }];
+        assert!(invoke
+            .tx_data::<TestFr, TestProof>(DEFAULT_FUNCTION)
+            .is_err());
+
+        let withdrawal_as_deposit = TestTxData {
+            delta: Delta {
+                token_amount: -100,
+                energy_amount: 0,
+                index: 0,
+                pool_id: 0,
+            }
+            .to_num(),
+            ..deposit
+        };
+        let err = InvokeScript::new(&withdrawal_as_deposit, "3P", DEFAULT_FUNCTION).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    fmt::{Display, Formatter},
+    io::{Error, ErrorKind, Result},
+};
+
+use crate::hash::{blake2b256, keccak256};
+
+/// Withdrawal parameters carried in the memo of a `TxType::Withdraw` transaction, right after the
+/// fee.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WithdrawData {
+    /// Amount of the chain's native coin sent to the receiver along with the tokens.
+    pub native_amount: u64,
+    pub receiver: ChainAddress,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ChainAddress {
+    Evm([u8; 20]),
+    /// Full 26-byte address including version, chain id and checksum.
+    Waves([u8; 26]),
+    Near(String),
+    Substrate([u8; 32]),
+    Solana([u8; 32]),
+    /// Version byte `0x41` followed by the 20-byte account hash.
+    Tron([u8; 21]),
+    /// Bech32 address such as `cosmos1...`.
+    Cosmos(String),
+}
+
+impl ChainAddress {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            ChainAddress::Evm(_) | ChainAddress::Substrate(_) | ChainAddress::Solana(_) => Ok(()),
+            ChainAddress::Waves(address) => validate_waves_address(address),
+            ChainAddress::Near(account_id) => validate_near_account_id(account_id),
+            ChainAddress::Tron(address) => validate_tron_address(address),
+            ChainAddress::Cosmos(address) => validate_bech32_address(address),
+        }
+    }
+}
+
+/// The address in the chain's usual text form: `0x`-prefixed hex for EVM and Substrate, base58
+/// for Waves and Solana, base58check for TRON and the account id or bech32 string otherwise.
+impl Display for ChainAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChainAddress::Evm(address) => write!(f, "0x{}", hex::encode(address)),
+            ChainAddress::Substrate(address) => write!(f, "0x{}", hex::encode(address)),
+            ChainAddress::Waves(address) => f.write_str(&bs58::encode(address).into_string()),
+            ChainAddress::Solana(address) => f.write_str(&bs58::encode(address).into_string()),
+            ChainAddress::Tron(address) => f.write_str(&crate::tron::address_to_base58(address)),
+            ChainAddress::Near(account_id) | ChainAddress::Cosmos(account_id) => {
+                f.write_str(account_id)
+            }
+        }
+    }
+}
+
+pub(crate) const TRON_ADDRESS_VERSION: u8 = 0x41;
+
+pub(crate) fn unexpected_address(address: &ChainAddress) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("address {address:?} is not supported by this chain"),
+    )
+}
+
+fn validate_waves_address(address: &[u8; 26]) -> Result<()> {
+    const VERSION: u8 = 1;
+
+    if address[0] != VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "invalid waves address version",
+        ));
+    }
+
+    let checksum = keccak256(&blake2b256(&address[..22]));
+    if checksum[..4] != address[22..] {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "invalid waves address checksum",
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_tron_address(address: &[u8; 21]) -> Result<()> {
+    if address[0] != TRON_ADDRESS_VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "invalid tron address version",
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_bech32_address(address: &str) -> Result<()> {
+    bech32::decode(address)
+        .map(|_| ())
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid bech32 address"))
+}
+
+fn validate_near_account_id(account_id: &str) -> Result<()> {
+    let invalid = || Error::new(ErrorKind::InvalidData, "invalid near account id");
+
+    if !(2..=64).contains(&account_id.len()) {
+        return Err(invalid());
+    }
+
+    let mut last_was_separator = true;
+    for c in account_id.chars() {
+        match c {
+            'a'..='z' | '0'..='9' => last_was_separator = false,
+            '-' | '_' | '.' if !last_was_separator => last_was_separator = true,
+            _ => return Err(invalid()),
+        }
+    }
+
+    if last_was_separator {
+        return Err(invalid());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_addresses() {
+        let near = |account_id: &str| ChainAddress::Near(account_id.to_owned()).validate();
+        assert!(near("alice.near").is_ok());
+        assert!(near("pool-1_x.testnet").is_ok());
+        assert!(near("Alice.near").is_err());
+        assert!(near("alice..near").is_err());
+        assert!(near(".alice").is_err());
+        assert!(near("a").is_err());
+
+        let mut waves = [0u8; 26];
+        waves[0] = 1;
+        waves[1] = b'W';
+        let checksum = keccak256(&blake2b256(&waves[..22]));
+        waves[22..].copy_from_slice(&checksum[..4]);
+        assert!(ChainAddress::Waves(waves).validate().is_ok());
+        waves[5] ^= 1;
+        assert!(ChainAddress::Waves(waves).validate().is_err());
+    }
+}
@@ -0,0 +1,466 @@
+//! Golden vectors: one encoded transaction per chain and tx type, checked into `tests/golden`.
+//! Each vector is decoded and compared with the fixture it was generated from, then re-encoded
+//! and compared byte for byte, so a change to either direction of a codec shows up here.
+//!
+//! The fixtures are deterministic but synthetic: proofs are not valid and memo ciphertexts are
+//! filler of the right size. After an intentional format change, regenerate the files with
+//! `UPDATE_GOLDEN=1 cargo test --features groth16 --test golden` and review the diff.
+//!
+//! Transactions captured from a chain go to `tests/golden/onchain/<chain>/<name>.hex` (or
+//! `.json`), next to a `<name>.source` file recording the network, transaction hash and block they
+//! were taken from. Every chain needs at least one of each tx type. They are decoded and
+//! re-encoded as well, and `UPDATE_GOLDEN` never touches them.
+#![cfg(feature = "groth16")]
+
+use std::{fs, path::PathBuf};
+
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use byteorder::{LittleEndian, WriteBytesExt};
+use fawkes_crypto::{
+    backend::bellman_groth16::{
+        engines::Bn256,
+        group::{G1Point, G2Point},
+        prover::Proof,
+    },
+    engines::bn256::Fr,
+    ff_uint::{Num, PrimeField, Uint},
+};
+use sha3::Keccak256;
+use zeropool_tx::{
+    cosmwasm::CosmWasm,
+    delta::Delta,
+    evm::Evm,
+    memo::MemoLayout,
+    near::Near,
+    solana::Solana,
+    starknet::{self, Felt},
+    substrate::Substrate,
+    tron::Tron,
+    waves::Waves,
+    withdraw::{ChainAddress, WithdrawData},
+    AssetId, Codec, TxData, TxType,
+};
+
+type Tx = TxData<Fr, Proof<Bn256>>;
+
+const TX_TYPES: [TxType; 3] = [TxType::Deposit, TxType::Transfer, TxType::Withdraw];
+
+/// How a vector is stored on disk.
+#[derive(Clone, Copy)]
+enum Format {
+    Hex,
+    /// For text encodings such as CosmWasm's JSON messages.
+    Raw,
+}
+
+/// Chain-specific parts of the fixtures.
+struct Chain {
+    name: &'static str,
+    format: Format,
+    asset_id: AssetId,
+    receiver: ChainAddress,
+    /// Size of the extra data (e.g. the depositor's signature) for each tx type.
+    extra_data_len: fn(TxType) -> usize,
+}
+
+fn signed_deposit(tx_type: TxType) -> usize {
+    if tx_type == TxType::Deposit {
+        64
+    } else {
+        0
+    }
+}
+
+fn no_extra_data(_: TxType) -> usize {
+    0
+}
+
+fn pattern(seed: u8, len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|i| seed.wrapping_add((i as u8).wrapping_mul(37)))
+        .collect()
+}
+
+/// Deterministic full-width field element.
+fn field<Fp: PrimeField>(seed: u64) -> Num<Fp> {
+    -Num::from(seed * 0x1_0000_0001 + 7)
+}
+
+fn fixture<C: Codec>(chain: &Chain, tx_type: TxType) -> Tx {
+    let seed = tx_type as u64 * 100;
+    let proof = |offset: u64| Proof {
+        a: G1Point(field(seed + offset + 1), field(seed + offset + 2)),
+        b: G2Point(
+            (field(seed + offset + 3), field(seed + offset + 4)),
+            (field(seed + offset + 5), field(seed + offset + 6)),
+        ),
+        c: G1Point(field(seed + offset + 7), field(seed + offset + 8)),
+    };
+
+    let (token_amount, energy_amount) = match tx_type {
+        TxType::Deposit => (1_000_000_000, 0),
+        TxType::Transfer => (0, 0),
+        TxType::Withdraw => (-500_000_000, -12_345),
+    };
+    let delta = Delta {
+        token_amount,
+        energy_amount,
+        index: 1280,
+        pool_id: 0,
+    };
+
+    let mut memo = Vec::new();
+    memo.write_u64::<C::MemoOrder>(100_000_000).unwrap();
+    if tx_type == TxType::Withdraw {
+        let data = WithdrawData {
+            native_amount: 25_000_000,
+            receiver: chain.receiver.clone(),
+        };
+        C::write_withdraw_data(&data, &mut memo).unwrap();
+    }
+
+    // An account and one note with the default layout.
+    let layout = MemoLayout::default();
+    memo.write_u32::<LittleEndian>(2).unwrap();
+    for hash in [field::<Fr>(seed + 30), field(seed + 31)] {
+        let mut bytes = [0u8; 32];
+        hash.to_uint().0.put_little_endian(&mut bytes);
+        memo.extend_from_slice(&bytes);
+    }
+    memo.extend(pattern(0x40, 32));
+    memo.extend(pattern(0x50, 32 * 2 + 16));
+    memo.extend(pattern(0x60, layout.account_ciphertext_size));
+    memo.extend(pattern(0x70, 32 + layout.note_ciphertext_size));
+
+    TxData {
+        tx_type,
+        proof: proof(0),
+        tree_proof: proof(10),
+        root_after: field(seed + 20),
+        delta: delta.to_num(),
+        out_commit: field(seed + 21),
+        nullifier: field(seed + 22),
+        memo,
+        extra_data: pattern(0x90, (chain.extra_data_len)(tx_type)),
+        asset_id: chain.asset_id.clone(),
+    }
+}
+
+fn golden_path(chain: &str, tx_type: TxType, extension: &str) -> PathBuf {
+    let tx_type = match tx_type {
+        TxType::Deposit => "deposit",
+        TxType::Transfer => "transfer",
+        TxType::Withdraw => "withdraw",
+    };
+
+    [env!("CARGO_MANIFEST_DIR"), "tests", "golden", chain]
+        .iter()
+        .collect::<PathBuf>()
+        .join(format!("{tx_type}.{extension}"))
+}
+
+/// Returns the checked-in vector, or writes `encoded` as the new one with `UPDATE_GOLDEN` set.
+fn golden(path: PathBuf, encoded: String) -> String {
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &encoded).unwrap();
+    }
+
+    fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("{}: {err}, run with UPDATE_GOLDEN=1", path.display()))
+}
+
+/// Compares the encoding of the fixture with the checked-in vector and returns the vector.
+fn check_write<C: Codec>(chain: &Chain, tx: &Tx) -> (PathBuf, Vec<u8>) {
+    let mut encoded = Vec::new();
+    C::write(tx, &mut encoded).unwrap();
+    let (extension, text) = match chain.format {
+        Format::Hex => ("hex", format!("{}\n", hex::encode(&encoded))),
+        Format::Raw => ("json", String::from_utf8(encoded).unwrap()),
+    };
+    let path = golden_path(chain.name, tx.tx_type, extension);
+    let expected = golden(path.clone(), text.clone());
+    assert_eq!(text, expected, "{} changed", path.display());
+
+    let bytes = match chain.format {
+        Format::Hex => hex::decode(expected.trim_end()).unwrap(),
+        Format::Raw => expected.into_bytes(),
+    };
+    (path, bytes)
+}
+
+fn check<C: Codec>(chain: Chain) {
+    for tx_type in TX_TYPES {
+        let tx = fixture::<C>(&chain, tx_type);
+        let (path, bytes) = check_write::<C>(&chain, &tx);
+
+        let decoded: Tx = C::read(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded, tx, "{}", path.display());
+
+        let mut reencoded = Vec::new();
+        C::write(&decoded, &mut reencoded).unwrap();
+        assert_eq!(reencoded, bytes, "{} does not re-encode", path.display());
+
+        assert_eq!(decoded.fee::<C>().unwrap(), 100_000_000);
+        assert_eq!(
+            decoded
+                .parse_memo::<C>(MemoLayout::default())
+                .unwrap()
+                .num_items(),
+            2
+        );
+        if tx_type == TxType::Withdraw {
+            assert_eq!(
+                decoded.withdraw_data::<C>().unwrap().receiver,
+                chain.receiver
+            );
+        }
+    }
+}
+
+/// Decodes the captured vectors of `chain` and, with `reencode`, checks that they re-encode.
+fn check_onchain<C: Codec>(chain: &Chain, reencode: bool) {
+    let dir = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "golden",
+        "onchain",
+        chain.name,
+    ]
+    .iter()
+    .collect::<PathBuf>();
+    let entries = fs::read_dir(&dir).unwrap_or_else(|err| {
+        panic!(
+            "{}: {err}, captured vectors are required for every chain",
+            dir.display()
+        )
+    });
+    let extension = match chain.format {
+        Format::Hex => "hex",
+        Format::Raw => "json",
+    };
+
+    let mut tx_types = Vec::new();
+    for path in entries.map(|entry| entry.unwrap().path()) {
+        if path.extension().is_none_or(|ext| ext != extension) {
+            continue;
+        }
+        assert!(
+            path.with_extension("source").is_file(),
+            "{} has no .source file",
+            path.display()
+        );
+
+        let text = fs::read_to_string(&path).unwrap();
+        let bytes = match chain.format {
+            Format::Hex => hex::decode(text.trim().trim_start_matches("0x")).unwrap(),
+            Format::Raw => text.into_bytes(),
+        };
+        let decoded: Tx =
+            C::read(&mut &bytes[..]).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+        if reencode {
+            let mut reencoded = Vec::new();
+            C::write(&decoded, &mut reencoded).unwrap();
+            assert_eq!(reencoded, bytes, "{} does not re-encode", path.display());
+        }
+        tx_types.push(decoded.tx_type);
+    }
+
+    for tx_type in TX_TYPES {
+        assert!(
+            tx_types.contains(&tx_type),
+            "{} has no captured {tx_type:?}",
+            dir.display()
+        );
+    }
+}
+
+fn waves_address() -> [u8; 26] {
+    let mut address = [0u8; 26];
+    address[0] = 1;
+    address[1] = b'W';
+    address[2..22].copy_from_slice(&pattern(0xa0, 20));
+    let checksum = Keccak256::digest(Blake2b::<U32>::digest(&address[..22]));
+    address[22..].copy_from_slice(&checksum[..4]);
+    address
+}
+
+fn evm() -> Chain {
+    Chain {
+        name: "evm",
+        format: Format::Hex,
+        asset_id: AssetId::None,
+        receiver: ChainAddress::Evm(pattern(0xd8, 20).try_into().unwrap()),
+        extra_data_len: signed_deposit,
+    }
+}
+
+fn tron() -> Chain {
+    let mut address = [0x41; 21];
+    address[1..].copy_from_slice(&pattern(0x1c, 20));
+
+    Chain {
+        name: "tron",
+        format: Format::Hex,
+        asset_id: AssetId::None,
+        receiver: ChainAddress::Tron(address),
+        extra_data_len: signed_deposit,
+    }
+}
+
+fn near() -> Chain {
+    Chain {
+        name: "near",
+        format: Format::Hex,
+        asset_id: AssetId::AccountId("usdt.tether-token.near".to_owned()),
+        receiver: ChainAddress::Near("alice.near".to_owned()),
+        extra_data_len: no_extra_data,
+    }
+}
+
+fn substrate() -> Chain {
+    Chain {
+        name: "substrate",
+        format: Format::Hex,
+        asset_id: AssetId::None,
+        receiver: ChainAddress::Substrate(pattern(0xd4, 32).try_into().unwrap()),
+        // Transfers and withdrawals end with the relayer's public key and signature.
+        extra_data_len: |tx_type| if tx_type == TxType::Deposit { 0 } else { 96 },
+    }
+}
+
+fn waves() -> Chain {
+    Chain {
+        name: "waves",
+        format: Format::Hex,
+        asset_id: AssetId::Id32(pattern(0x0b, 32).try_into().unwrap()),
+        receiver: ChainAddress::Waves(waves_address()),
+        // Deposits end with the depositor's public key and signature.
+        extra_data_len: |tx_type| if tx_type == TxType::Deposit { 96 } else { 0 },
+    }
+}
+
+fn solana() -> Chain {
+    Chain {
+        name: "solana",
+        format: Format::Hex,
+        asset_id: AssetId::None,
+        receiver: ChainAddress::Solana(pattern(0x3b, 32).try_into().unwrap()),
+        extra_data_len: signed_deposit,
+    }
+}
+
+fn cosmwasm() -> Chain {
+    Chain {
+        name: "cosmwasm",
+        format: Format::Raw,
+        asset_id: AssetId::AccountId("uatom".to_owned()),
+        receiver: ChainAddress::Cosmos("cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu".to_owned()),
+        extra_data_len: signed_deposit,
+    }
+}
+
+#[test]
+fn test_golden_evm() {
+    check::<Evm>(evm());
+}
+
+#[test]
+fn test_golden_tron() {
+    check::<Tron>(tron());
+}
+
+#[test]
+fn test_golden_near() {
+    check::<Near>(near());
+}
+
+/// Substrate's `read` expects the pallet call data, which has a different layout from what
+/// `write` produces, so the fixtures only check the encoding and captured call data is only
+/// decoded.
+#[test]
+fn test_golden_substrate() {
+    let chain = substrate();
+    for tx_type in TX_TYPES {
+        check_write::<Substrate>(&chain, &fixture::<Substrate>(&chain, tx_type));
+    }
+}
+
+#[test]
+fn test_golden_waves() {
+    check::<Waves>(waves());
+}
+
+#[test]
+fn test_golden_solana() {
+    check::<Solana>(solana());
+}
+
+#[test]
+fn test_golden_cosmwasm() {
+    check::<CosmWasm>(cosmwasm());
+}
+
+#[test]
+fn test_onchain_evm() {
+    check_onchain::<Evm>(&evm(), true);
+}
+
+#[test]
+fn test_onchain_tron() {
+    check_onchain::<Tron>(&tron(), true);
+}
+
+#[test]
+fn test_onchain_near() {
+    check_onchain::<Near>(&near(), true);
+}
+
+#[test]
+fn test_onchain_substrate() {
+    check_onchain::<Substrate>(&substrate(), false);
+}
+
+#[test]
+fn test_onchain_waves() {
+    check_onchain::<Waves>(&waves(), true);
+}
+
+#[test]
+fn test_onchain_solana() {
+    check_onchain::<Solana>(&solana(), true);
+}
+
+#[test]
+fn test_onchain_cosmwasm() {
+    check_onchain::<CosmWasm>(&cosmwasm(), true);
+}
+
+/// Felts are stored one per line. The Starknet encoding treats the memo as opaque bytes, so the
+/// EVM fixtures are reused.
+#[test]
+fn test_golden_starknet() {
+    for tx_type in TX_TYPES {
+        let tx = fixture::<Evm>(&evm(), tx_type);
+
+        let felts = starknet::encode(&tx).unwrap();
+        let text: String = felts
+            .iter()
+            .map(|felt| format!("0x{}\n", hex::encode(felt.to_be_bytes())))
+            .collect();
+        let path = golden_path("starknet", tx_type, "felts");
+        let expected = golden(path.clone(), text.clone());
+        assert_eq!(text, expected, "{} changed", path.display());
+
+        let felts = expected
+            .lines()
+            .map(|line| {
+                let bytes = hex::decode(line.trim_start_matches("0x")).unwrap();
+                Felt::from_be_bytes(bytes.try_into().unwrap()).unwrap()
+            })
+            .collect::<Vec<_>>();
+        let decoded: Tx = starknet::decode(&felts).unwrap();
+        assert_eq!(decoded, tx, "{}", path.display());
+        assert_eq!(starknet::encode(&decoded).unwrap(), felts);
+    }
+}